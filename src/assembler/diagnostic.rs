@@ -0,0 +1,170 @@
+use std::{error::Error, fmt, ops::Range};
+
+use super::preprocess::SourceLine;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Something the assembler can't make sense of; no image is produced.
+    Error,
+    /// Probably a mistake, but the program still assembles.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem in the source, pointing at the line and the part of it that caused it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub file: String,
+    /// 1-based line number in `file`.
+    pub line: usize,
+    /// Byte range in the line the diagnostic is about.
+    pub span: Range<usize>,
+    /// The line itself, to show it under the message.
+    pub source_line: String,
+    /// A hint on how to fix the problem.
+    pub help: Option<String>,
+    /// Where the line came from if it was expanded from a macro.
+    pub note: Option<String>,
+}
+
+impl Diagnostic {
+    pub(super) fn new(
+        severity: Severity,
+        line: &SourceLine,
+        span: Range<usize>,
+        message: impl Into<String>,
+        help: Option<String>,
+    ) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            file: line.file.to_string(),
+            line: line.number,
+            span,
+            source_line: line.text.clone(),
+            help,
+            note: line.expansion.as_deref().map(str::to_string),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// 1-based column of the start of the span, counted in characters.
+    pub fn column(&self) -> usize {
+        self.source_line
+            .get(..self.span.start)
+            .map_or(0, |before| before.chars().count())
+            + 1
+    }
+
+    /// Renders the diagnostic the way compilers do:
+    ///
+    /// ```text
+    /// error: #16 doesn't fit in 5 bits
+    ///  --> count.asm:2:21
+    ///   |
+    /// 2 |         ADD R1, R1, #16
+    ///   |                     ^^^
+    ///   = help: ADD and AND take immediates from -16 to 15; put larger values in a register first
+    /// ```
+    pub fn render(&self) -> String {
+        let number = self.line.to_string();
+        let gutter = " ".repeat(number.len());
+
+        // keep tabs so the carets line up with the source however wide tabs are
+        let indent: String = self
+            .source_line
+            .get(..self.span.start)
+            .unwrap_or_default()
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = self
+            .source_line
+            .get(self.span.clone())
+            .map_or(0, |text| text.chars().count())
+            .max(1);
+
+        let mut text = format!(
+            "{}: {}\n{gutter}--> {}:{}:{}\n{gutter} |\n{number} | {}\n{gutter} | {indent}{}\n",
+            self.severity,
+            self.message,
+            self.file,
+            self.line,
+            self.column(),
+            self.source_line,
+            "^".repeat(width)
+        );
+        if let Some(note) = &self.note {
+            text.push_str(&format!("{gutter} = note: {note}\n"));
+        }
+        if let Some(help) = &self.help {
+            text.push_str(&format!("{gutter} = help: {help}\n"));
+        }
+
+        text
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}: {}",
+            self.file,
+            self.line,
+            self.column(),
+            self.severity,
+            self.message
+        )
+    }
+}
+
+impl Error for Diagnostic {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let diagnostic = Diagnostic {
+            severity: Severity::Error,
+            message: "#16 doesn't fit in 5 bits".to_string(),
+            file: "count.asm".to_string(),
+            line: 12,
+            span: 13..16,
+            source_line: "\tADD R1, R1, #16\t; too big".to_string(),
+            help: Some("use a register".to_string()),
+            note: None,
+        };
+
+        assert_eq!(diagnostic.column(), 14);
+        assert_eq!(
+            diagnostic.render(),
+            "error: #16 doesn't fit in 5 bits
+  --> count.asm:12:14
+   |
+12 | \tADD R1, R1, #16\t; too big
+   | \t            ^^^
+   = help: use a register
+"
+        );
+        assert_eq!(
+            diagnostic.to_string(),
+            "count.asm:12:14: error: #16 doesn't fit in 5 bits"
+        );
+    }
+}
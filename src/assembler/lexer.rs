@@ -0,0 +1,276 @@
+use std::ops::Range;
+
+use crate::hardware::symbol::is_label;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A mnemonic, register or label.
+    Word(String),
+    /// A pseudo-op such as `.ORIG`, without the dot and in upper case.
+    Directive(String),
+    Number(i32),
+    /// A string literal with its escapes already processed.
+    Str(String),
+    Comma,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Byte range of the token in its line.
+    pub span: Range<usize>,
+}
+
+/// A lexical error and the part of the line it's about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexError {
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+/// Splits one line of assembly into tokens, dropping the `;` comment.
+///
+/// Numbers are decimal (`#10`, `#-3`, `10`), hex (`x3000`, `0x3000`) or binary (`b0101`, `0b0101`).
+/// A word starting with `x` or `b` that is a valid number in that base is a number,
+/// so `xBAD` is hex while `BAD` and `xylophone` are labels.
+/// A label at the start of the line may end with a colon, `LOOP:`, which isn't part of its span.
+pub fn tokenize(line: &str) -> Result<Vec<Token>, LexError> {
+    let bytes = line.as_bytes();
+    let mut tokens = Vec::new();
+    let mut start = 0;
+
+    while start < bytes.len() {
+        let c = bytes[start];
+
+        if c == b';' {
+            break;
+        }
+        if c.is_ascii_whitespace() {
+            start += 1;
+            continue;
+        }
+        if c == b',' {
+            tokens.push(Token {
+                kind: TokenKind::Comma,
+                span: start..start + 1,
+            });
+            start += 1;
+            continue;
+        }
+        if c == b'"' {
+            let (text, end) = string_literal(line, start)?;
+            tokens.push(Token {
+                kind: TokenKind::Str(text),
+                span: start..end,
+            });
+            start = end;
+            continue;
+        }
+
+        let end = start
+            + bytes[start..]
+                .iter()
+                .position(|c| c.is_ascii_whitespace() || matches!(c, b',' | b';' | b'"'))
+                .unwrap_or(bytes.len() - start);
+        let text = &line[start..end];
+        let span = start..end;
+        start = end;
+
+        let kind = if let Some(directive) = text.strip_prefix('.') {
+            if !is_label(directive) {
+                return Err(LexError {
+                    message: format!("`{text}` is not a valid pseudo-op"),
+                    span,
+                });
+            }
+            TokenKind::Directive(directive.to_ascii_uppercase())
+        } else if let Some(number) = parse_number(text) {
+            TokenKind::Number(number.map_err(|message| LexError {
+                message,
+                span: span.clone(),
+            })?)
+        } else if is_label(text) {
+            TokenKind::Word(text.to_string())
+        } else if let Some(label) = text
+            .strip_suffix(':')
+            .filter(|label| tokens.is_empty() && is_label(label))
+        {
+            // the assembler warns about the colon
+            tokens.push(Token {
+                kind: TokenKind::Word(label.to_string()),
+                span: span.start..span.end - 1,
+            });
+            continue;
+        } else {
+            return Err(LexError {
+                message: format!("unexpected `{text}`"),
+                span,
+            });
+        };
+
+        tokens.push(Token { kind, span });
+    }
+
+    Ok(tokens)
+}
+
+/// `None` if `text` isn't meant as a number, `Some(Err(..))` if it is but is malformed.
+fn parse_number(text: &str) -> Option<Result<i32, String>> {
+    let (digits, radix) = match text.as_bytes() {
+        [b'#', ..] => (&text[1..], 10),
+        [b'0', b'x' | b'X', ..] => (&text[2..], 16),
+        [b'0', b'b' | b'B', ..] => (&text[2..], 2),
+        [b'x' | b'X', rest @ ..] if is_number(rest, 16) => (&text[1..], 16),
+        [b'b' | b'B', rest @ ..] if is_number(rest, 2) => (&text[1..], 2),
+        [b'-' | b'0'..=b'9', ..] => (text, 10),
+        _ => return None,
+    };
+
+    let (negative, digits) = match digits.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, digits),
+    };
+    let number = match digits.is_empty() {
+        true => None,
+        false => i32::from_str_radix(digits, radix).ok(),
+    };
+
+    Some(match number {
+        Some(number) if negative => Ok(-number),
+        Some(number) => Ok(number),
+        None => Err(format!("`{text}` is not a valid number")),
+    })
+}
+
+fn is_number(digits: &[u8], radix: u32) -> bool {
+    let digits = digits.strip_prefix(b"-").unwrap_or(digits);
+
+    !digits.is_empty() && digits.iter().all(|c| (*c as char).is_digit(radix))
+}
+
+/// Reads the string literal starting at the `"` at `start`; returns it and the index past its end.
+fn string_literal(line: &str, start: usize) -> Result<(String, usize), LexError> {
+    let mut text = String::new();
+    let mut chars = line[start + 1..].char_indices();
+
+    while let Some((index, c)) = chars.next() {
+        let index = start + 1 + index;
+        match c {
+            '"' => return Ok((text, index + 1)),
+            '\\' => {
+                let escaped = match chars.next() {
+                    Some((_, 'n')) => '\n',
+                    Some((_, 't')) => '\t',
+                    Some((_, 'r')) => '\r',
+                    Some((_, 'e')) => '\x1b',
+                    Some((_, '0')) => '\0',
+                    Some((_, '\\')) => '\\',
+                    Some((_, '"')) => '"',
+                    Some((_, '\'')) => '\'',
+                    Some((_, other)) => {
+                        return Err(LexError {
+                            message: format!("unknown escape sequence `\\{other}`"),
+                            span: index..index + 1 + other.len_utf8(),
+                        })
+                    }
+                    None => break,
+                };
+                text.push(escaped);
+            }
+            _ => text.push(c),
+        }
+    }
+
+    Err(LexError {
+        message: "unterminated string".to_string(),
+        span: start..line.len(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn kinds(line: &str) -> Vec<TokenKind> {
+        tokenize(line)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        use TokenKind::*;
+
+        assert_eq!(
+            kinds("LOOP  ADD R1, R1, #-1 ; count down"),
+            [
+                Word("LOOP".into()),
+                Word("ADD".into()),
+                Word("R1".into()),
+                Comma,
+                Word("R1".into()),
+                Comma,
+                Number(-1)
+            ]
+        );
+        assert_eq!(
+            kinds(".orig x3000"),
+            [Directive("ORIG".into()), Number(0x3000)]
+        );
+        assert_eq!(
+            kinds("MSG .STRINGZ \"a;\\\"b\\n\""),
+            [
+                Word("MSG".into()),
+                Directive("STRINGZ".into()),
+                Str("a;\"b\n".into())
+            ]
+        );
+        assert_eq!(
+            tokenize("LOOP: BRp LOOP").unwrap()[0],
+            Token {
+                kind: Word("LOOP".into()),
+                span: 0..4,
+            }
+        );
+        assert!(tokenize("BRp LOOP:").is_err());
+    }
+
+    #[test]
+    fn test_numbers() {
+        use TokenKind::*;
+
+        assert_eq!(
+            kinds("#10 10 -3 x1F 0x1f b101 0b101 xBAD"),
+            [
+                Number(10),
+                Number(10),
+                Number(-3),
+                Number(0x1f),
+                Number(0x1f),
+                Number(5),
+                Number(5),
+                Number(0xbad)
+            ]
+        );
+        assert_eq!(
+            kinds("BAD xylophone b2"),
+            [
+                Word("BAD".into()),
+                Word("xylophone".into()),
+                Word("b2".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = tokenize("  .FILL #1x").unwrap_err();
+        assert_eq!(err.span, 8..11);
+
+        let err = tokenize("  .STRINGZ \"abc").unwrap_err();
+        assert_eq!(err.message, "unterminated string");
+        assert_eq!(err.span, 11..15);
+    }
+}
@@ -0,0 +1,210 @@
+use std::ops::Range;
+
+use super::preprocess::SourceLine;
+use crate::hardware::instruction::{Instruction, Operand};
+
+/// What the words of a statement are, which decides how they're shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Contents {
+    /// One instruction, shown split into its fields.
+    Instruction,
+    /// `.FILL` and `.STRINGZ` words.
+    Data,
+    /// A `.BLKW`, shown as its first word and a count of the rest.
+    Block,
+}
+
+/// Where a statement ended up in the image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Placement {
+    /// Index of the statement's line in the preprocessed program.
+    pub line: usize,
+    pub address: u16,
+    /// The statement's words, as a range of the image's words.
+    pub words: Range<usize>,
+    pub contents: Contents,
+    /// The label a PCoffset or `.FILL` refers to, and its address.
+    pub target: Option<(String, u16)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Row {
+    line: SourceLine,
+    placement: Option<(Placement, Vec<u16>)>,
+}
+
+/// The program side by side with what it assembled to, for checking hand assembly:
+///
+/// ```text
+/// ; hello.asm
+/// Address  Hex    Binary                 Target            Line  Source
+///                                                             1          .ORIG x3000
+/// x3000    xE002  1110 000 000000010     HELLO (x3003)        2          LEA R0, HELLO
+/// x3001    xF022  1111 0000 00100010                          3          PUTS
+/// ```
+///
+/// Instructions are split into the fields of their encoding; lines from macros are marked with `+`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listing {
+    rows: Vec<Row>,
+}
+
+impl Listing {
+    /// Pairs every line of the program with the statement placed from it, if any.
+    /// `placements` are in the order of the program.
+    pub(super) fn new(lines: &[SourceLine], placements: Vec<Placement>, words: &[u16]) -> Self {
+        let mut placements = placements.into_iter().peekable();
+        let rows = lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| Row {
+                line: line.clone(),
+                placement: placements.next_if(|placement| placement.line == index).map(
+                    |placement| {
+                        let words = words[placement.words.clone()].to_vec();
+                        (placement, words)
+                    },
+                ),
+            })
+            .collect();
+
+        Self { rows }
+    }
+
+    /// The contents of the `.lst` file.
+    pub fn to_lst_file(&self) -> String {
+        let mut text = String::new();
+        let mut file = None;
+
+        for Row { line, placement } in &self.rows {
+            if file != Some(&line.file) {
+                if file.is_some() {
+                    text.push('\n');
+                }
+                text.push_str(&format!("; {}\n", line.file));
+                text.push_str(&format!(
+                    "{:<7}  {:<5}  {:<21}  {:<16}  {:>4}  Source\n",
+                    "Address", "Hex", "Binary", "Target", "Line"
+                ));
+                file = Some(&line.file);
+            }
+
+            let marker = if line.expansion.is_some() { "+" } else { " " };
+            let number = format!("{}{marker}", line.number);
+            let Some((placement, words)) = placement else {
+                push_row(&mut text, "", "", "", "", &number, &line.text);
+                continue;
+            };
+
+            let address = format!("x{:04X}", placement.address);
+            let target = placement
+                .target
+                .as_ref()
+                .map(|(label, address)| format!("{label} (x{address:04X})"))
+                .unwrap_or_default();
+            let Some((first, rest)) = words.split_first() else {
+                // an empty .BLKW or .STRINGZ has an address but no words
+                push_row(&mut text, &address, "", "", &target, &number, &line.text);
+                continue;
+            };
+
+            let hex = format!("x{first:04X}");
+            let bits = binary(*first, placement.contents);
+            push_row(
+                &mut text, &address, &hex, &bits, &target, &number, &line.text,
+            );
+
+            match placement.contents {
+                Contents::Block if !rest.is_empty() => {
+                    let last = placement.address.wrapping_add(rest.len() as u16);
+                    let plural = if rest.len() == 1 { "" } else { "s" };
+                    text.push_str(&format!(
+                        "{:<7}  x0000  ({} more word{plural} up to x{last:04X})\n",
+                        "...",
+                        rest.len()
+                    ));
+                }
+                _ => {
+                    for (offset, word) in rest.iter().enumerate() {
+                        let address = placement.address.wrapping_add(offset as u16 + 1);
+                        push_row(
+                            &mut text,
+                            &format!("x{address:04X}"),
+                            &format!("x{word:04X}"),
+                            &binary(*word, placement.contents),
+                            "",
+                            "",
+                            "",
+                        );
+                    }
+                }
+            }
+        }
+
+        text
+    }
+}
+
+fn push_row(
+    text: &mut String,
+    address: &str,
+    hex: &str,
+    binary: &str,
+    target: &str,
+    number: &str,
+    source: &str,
+) {
+    let row = format!("{address:<7}  {hex:<5}  {binary:<21}  {target:<16}  {number:>5} {source}");
+    text.push_str(row.trim_end());
+    text.push('\n');
+}
+
+/// The word in binary: instructions split into their fields, anything else into nibbles.
+fn binary(word: u16, contents: Contents) -> String {
+    let widths: &[usize] = match contents {
+        Contents::Instruction => instruction_fields(word),
+        Contents::Data | Contents::Block => &[4, 4, 4, 4],
+    };
+
+    let bits = format!("{word:016b}");
+    let mut fields = Vec::with_capacity(widths.len());
+    let mut start = 0;
+    for width in widths {
+        fields.push(&bits[start..start + width]);
+        start += width;
+    }
+
+    fields.join(" ")
+}
+
+/// Widths of the fields of an instruction's encoding, from the opcode down.
+fn instruction_fields(word: u16) -> &'static [usize] {
+    match Instruction::decode(word) {
+        // DR, SR1, and imm5 or SR2
+        Instruction::Add {
+            operand: Operand::Immediate(_),
+            ..
+        }
+        | Instruction::And {
+            operand: Operand::Immediate(_),
+            ..
+        } => &[4, 3, 3, 1, 5],
+        Instruction::Add { .. } | Instruction::And { .. } => &[4, 3, 3, 1, 2, 3],
+        // BR: nzp, PCoffset9; LD, ST, LDI, STI, LEA: DR or SR, PCoffset9
+        Instruction::Br { .. }
+        | Instruction::Ld { .. }
+        | Instruction::St { .. }
+        | Instruction::Ldi { .. }
+        | Instruction::Sti { .. }
+        | Instruction::Lea { .. } => &[4, 3, 9],
+        Instruction::Jsr { .. } => &[4, 1, 11],
+        Instruction::Jsrr { .. } => &[4, 1, 2, 3, 6],
+        // DR or SR, BaseR, offset6; NOT: DR, SR; JMP: BaseR
+        Instruction::Ldr { .. }
+        | Instruction::Str { .. }
+        | Instruction::Not { .. }
+        | Instruction::Jmp { .. } => &[4, 3, 3, 6],
+        Instruction::Trap { .. } => &[4, 4, 8],
+        Instruction::Rti { .. } | Instruction::Reserved { .. } => &[4, 12],
+    }
+}
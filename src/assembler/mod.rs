@@ -0,0 +1,1314 @@
+//! An assembler for LC-3 assembly, producing the same `.obj` and `.sym` files as `lc3as`.
+//!
+//! ```text
+//!         .ORIG x3000
+//!         LEA R0, HELLO   ; labels, registers and #decimal, xhex or b0101 literals
+//!         PUTS            ; trap aliases: GETC, OUT, PUTS, IN, PUTSP, HALT
+//!         HALT
+//! HELLO   .STRINGZ "Hello, World!"
+//!         .END
+//! ```
+//!
+//! Programs split into modules are assembled with [`assemble_module`] instead: a module can leave
+//! out `.ORIG` to be placed by the linker, use labels of other modules with `.IMPORT LABEL`
+//! and let them use its own with `.EXPORT LABEL`. See [`crate::linker`].
+
+mod diagnostic;
+mod lexer;
+mod listing;
+mod parser;
+mod preprocess;
+
+use std::{
+    collections::HashSet,
+    fs, io,
+    ops::{Range, RangeInclusive},
+    path::Path,
+    rc::Rc,
+};
+
+use crate::{
+    hardware::{
+        image::Image,
+        instruction::{self, Instruction},
+        symbol::SymbolTable,
+    },
+    linker::{Module, ModuleSymbol, Relocation, RelocationKind},
+};
+pub use diagnostic::{Diagnostic, Severity};
+use lexer::tokenize;
+pub use listing::Listing;
+use listing::{Contents, Placement};
+use parser::{
+    parse_statement, Directive, Mnemonic, Operand, OperandKind, Operation, OperationKind,
+};
+use preprocess::{Preprocessor, SourceLine};
+
+/// The output of the assembler: the image to write to the `.obj` file, the labels for the `.sym` file
+/// and the listing for the `.lst` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assembly {
+    pub image: Image,
+    pub symbols: SymbolTable,
+    pub listing: Listing,
+    /// The program as a relocatable object, for the linker.
+    /// For a module that imports labels, this is the only complete output: the words of `image`
+    /// that refer to imported labels are left as 0.
+    pub module: Module,
+    /// Problems that didn't stop the program from assembling.
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// A statement that takes up memory, with the address it starts at.
+struct Located {
+    line: usize,
+    address: u16,
+    operation: Operation,
+}
+
+/// Where a label is defined, imported or exported.
+struct Definition {
+    label: String,
+    line: usize,
+    span: Range<usize>,
+}
+
+struct Assembler {
+    /// The program after preprocessing; diagnostics refer to lines by their index in it.
+    lines: Vec<SourceLine>,
+    /// The file being assembled, for diagnostics about the program as a whole.
+    file: Rc<str>,
+    diagnostics: Vec<(usize, Diagnostic)>,
+    symbols: SymbolTable,
+    definitions: Vec<Definition>,
+    used_labels: HashSet<String>,
+    /// The label the statement being encoded refers to, for the listing.
+    target: Option<(String, u16)>,
+    /// Whether this is a module, which may leave out `.ORIG` and import labels.
+    relocatable: bool,
+    /// The address the program was assembled for.
+    origin: u16,
+    imports: Vec<Definition>,
+    exports: Vec<Definition>,
+    relocations: Vec<Relocation>,
+}
+
+/// Assembles a whole program: a `.ORIG`, the statements it contains, and an optional `.END`.
+/// Everything after `.END` is ignored. `.INCLUDE`d files are looked up in the current directory.
+///
+/// The whole program is checked even after an error, so all the problems are reported at once:
+/// on failure, every error and warning is returned. Those of the preprocessor (`.INCLUDE`,
+/// `.DEFINE`, macros, ...) come first, followed by the others in the order of the program.
+pub fn assemble(source: &str) -> Result<Assembly, Vec<Diagnostic>> {
+    assemble_source("<source>", None, source, false)
+}
+
+/// Assembles the program in the file at `path`, see [`assemble`].
+/// `.INCLUDE`d files are looked up relative to the file including them.
+pub fn assemble_file<P: AsRef<Path>>(path: P) -> io::Result<Result<Assembly, Vec<Diagnostic>>> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+
+    Ok(assemble_source(
+        &path.display().to_string(),
+        Some(path),
+        &source,
+        false,
+    ))
+}
+
+/// Assembles a module to be linked with others, see [`Assembly::module`].
+/// Without a `.ORIG`, it's assembled as if it started at x0000.
+pub fn assemble_module(source: &str) -> Result<Assembly, Vec<Diagnostic>> {
+    assemble_source("<source>", None, source, true)
+}
+
+/// Assembles the module in the file at `path`, see [`assemble_module`].
+pub fn assemble_module_file<P: AsRef<Path>>(
+    path: P,
+) -> io::Result<Result<Assembly, Vec<Diagnostic>>> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+
+    Ok(assemble_source(
+        &path.display().to_string(),
+        Some(path),
+        &source,
+        true,
+    ))
+}
+
+fn assemble_source(
+    file: &str,
+    path: Option<&Path>,
+    source: &str,
+    relocatable: bool,
+) -> Result<Assembly, Vec<Diagnostic>> {
+    let mut preprocessor = Preprocessor::new();
+    preprocessor.process_file(path, SourceLine::split(file, source));
+
+    let mut assembler = Assembler {
+        lines: preprocessor.output,
+        file: file.into(),
+        diagnostics: Vec::new(),
+        symbols: SymbolTable::new(),
+        definitions: Vec::new(),
+        used_labels: HashSet::new(),
+        target: None,
+        relocatable,
+        origin: 0,
+        imports: Vec::new(),
+        exports: Vec::new(),
+        relocations: Vec::new(),
+    };
+
+    let statements = assembler.parse();
+    // the listing stops where the assembler did, at `.END`
+    let end = statements
+        .last()
+        .filter(|(_, statement)| {
+            matches!(
+                statement.operation,
+                Some(Operation {
+                    kind: OperationKind::Directive(Directive::End),
+                    ..
+                })
+            )
+        })
+        .map_or(assembler.lines.len(), |(line, _)| line + 1);
+    let (origin, located) = assembler.layout(statements);
+    assembler.origin = origin.unwrap_or_default();
+    assembler.check_linkage();
+
+    let mut words = Vec::new();
+    let mut placements = Vec::new();
+    for statement in &located {
+        let start = words.len();
+        assembler.encode(statement, &mut words);
+        placements.push(Placement {
+            line: statement.line,
+            address: statement.address,
+            words: start..words.len(),
+            contents: match statement.operation.kind {
+                OperationKind::Instruction(_) => Contents::Instruction,
+                OperationKind::Directive(Directive::Blkw) => Contents::Block,
+                OperationKind::Directive(_) => Contents::Data,
+            },
+            target: assembler.target.take(),
+        });
+    }
+    assembler.check_unused_labels();
+
+    assembler
+        .diagnostics
+        .sort_by_key(|(line, diagnostic)| (*line, diagnostic.span.start));
+    let mut diagnostics = preprocessor.diagnostics;
+    diagnostics.extend(assembler.diagnostics.into_iter().map(|(_, d)| d));
+
+    if diagnostics.iter().any(Diagnostic::is_error) {
+        return Err(diagnostics);
+    }
+
+    let listing = Listing::new(&assembler.lines[..end], placements, &words);
+    let module = Module {
+        origin: if relocatable {
+            origin
+        } else {
+            Some(assembler.origin)
+        },
+        words: words.clone(),
+        symbols: assembler
+            .symbols
+            .iter()
+            .map(|(label, address)| ModuleSymbol {
+                name: label.to_string(),
+                offset: address.wrapping_sub(assembler.origin),
+                exported: assembler.exports.iter().any(|export| export.label == label),
+            })
+            .collect(),
+        imports: assembler
+            .imports
+            .into_iter()
+            .map(|import| import.label)
+            .collect(),
+        relocations: assembler.relocations,
+    };
+    Ok(Assembly {
+        image: Image {
+            origin: assembler.origin,
+            words,
+        },
+        symbols: assembler.symbols,
+        listing,
+        module,
+        warnings: diagnostics,
+    })
+}
+
+impl Assembler {
+    fn report(
+        &mut self,
+        severity: Severity,
+        line: usize,
+        span: Range<usize>,
+        message: impl Into<String>,
+        help: Option<String>,
+    ) {
+        let diagnostic = match self.lines.get(line) {
+            Some(source_line) => Diagnostic::new(severity, source_line, span, message, help),
+            // an empty program
+            None => Diagnostic::new(
+                severity,
+                &SourceLine::split(&self.file, "\n")[0],
+                span,
+                message,
+                help,
+            ),
+        };
+
+        self.diagnostics.push((line, diagnostic));
+    }
+
+    fn error(&mut self, line: usize, span: Range<usize>, message: impl Into<String>) {
+        self.report(Severity::Error, line, span, message, None);
+    }
+
+    /// Every statement up to `.END`; lines with syntax errors are reported and left out.
+    fn parse(&mut self) -> Vec<(usize, parser::Statement)> {
+        let mut statements = Vec::new();
+
+        for line in 0..self.lines.len() {
+            let statement = tokenize(&self.lines[line].text)
+                .map_err(|err| (err.span, err.message))
+                .and_then(|tokens| parse_statement(&tokens).map_err(|err| (err.span, err.message)));
+
+            match statement {
+                Ok(statement) => {
+                    if let Some((label, span)) = &statement.label {
+                        if self.lines[line].text[span.end..].starts_with(':') {
+                            self.report(
+                                Severity::Warning,
+                                line,
+                                span.start..span.end + 1,
+                                "labels don't take a colon",
+                                Some(format!("`{label}:` is read as `{label}`")),
+                            );
+                        }
+                    }
+                    let end = matches!(
+                        statement.operation,
+                        Some(Operation {
+                            kind: OperationKind::Directive(Directive::End),
+                            ..
+                        })
+                    );
+                    statements.push((line, statement));
+                    if end {
+                        break;
+                    }
+                }
+                Err((span, message)) => self.error(line, span, message),
+            }
+        }
+
+        statements
+    }
+
+    /// First pass: finds the origin, the address of every statement and the value of every label.
+    /// The origin is `None` for a module without `.ORIG`.
+    fn layout(
+        &mut self,
+        statements: Vec<(usize, parser::Statement)>,
+    ) -> (Option<u16>, Vec<Located>) {
+        let mut origin = None;
+        let mut address: u32 = 0;
+        let mut located = Vec::new();
+
+        for (line, statement) in statements {
+            let operation = statement.operation;
+            let is_orig = matches!(
+                operation,
+                Some(Operation {
+                    kind: OperationKind::Directive(Directive::Orig),
+                    ..
+                })
+            );
+            // they take up no memory, so they can come before `.ORIG`
+            let is_linkage = statement.label.is_none()
+                && matches!(
+                    operation,
+                    Some(Operation {
+                        kind: OperationKind::Directive(Directive::Import | Directive::Export),
+                        ..
+                    })
+                );
+
+            if origin.is_none() && !is_orig && !is_linkage && !self.relocatable {
+                let span = match (&statement.label, &operation) {
+                    (Some((_, span)), _) => span.clone(),
+                    (None, Some(operation)) => operation.span.clone(),
+                    (None, None) => continue,
+                };
+                self.report(
+                    Severity::Error,
+                    line,
+                    span,
+                    "the program has to start with .ORIG",
+                    Some("put `.ORIG x3000` (or wherever the program goes) first".to_string()),
+                );
+                // carry on as if it had, to check the rest of the program
+                origin = Some(0x3000);
+                address = 0x3000;
+            }
+
+            if let Some((label, span)) = statement.label {
+                if let Some(previous) = self.symbols.address_of(&label) {
+                    self.error(
+                        line,
+                        span,
+                        format!("label `{label}` is already defined, at x{previous:04X}"),
+                    );
+                } else {
+                    self.symbols.insert(&label, address as u16);
+                    self.definitions.push(Definition { label, line, span });
+                }
+            }
+
+            let Some(operation) = operation else {
+                continue;
+            };
+            if !self.check_arity(line, &operation) {
+                // still takes up a word, so the addresses after it are right
+                if let OperationKind::Instruction(_) = operation.kind {
+                    address += 1;
+                }
+                continue;
+            }
+
+            let size = match (&operation.kind, &operation.operands[..]) {
+                (OperationKind::Directive(Directive::Orig), [operand]) => {
+                    if is_orig && origin.is_some() {
+                        self.error(line, operation.span, "a program can only have one .ORIG");
+                        continue;
+                    }
+                    if !located.is_empty() {
+                        self.error(
+                            line,
+                            operation.span,
+                            ".ORIG has to come before the first instruction or data",
+                        );
+                        continue;
+                    }
+                    let value = self.number(line, operand, 0..=0xffff).unwrap_or(0x3000);
+                    origin = Some(value as u16);
+                    address = value as u32;
+                    continue;
+                }
+                (OperationKind::Directive(Directive::End), _) => break,
+                (
+                    OperationKind::Directive(directive @ (Directive::Import | Directive::Export)),
+                    [operand],
+                ) => {
+                    let OperandKind::Label(label) = &operand.kind else {
+                        self.error(line, operand.span.clone(), "expected a label");
+                        continue;
+                    };
+                    let definition = Definition {
+                        label: label.clone(),
+                        line,
+                        span: operand.span.clone(),
+                    };
+                    if *directive == Directive::Export {
+                        self.exports.push(definition);
+                        continue;
+                    }
+                    if !self.relocatable {
+                        self.report(
+                            Severity::Error,
+                            line,
+                            operation.span,
+                            ".IMPORT can only be used in a module",
+                            Some(
+                                "assemble it with `asm --module` and combine the modules with `link`"
+                                    .to_string(),
+                            ),
+                        );
+                    }
+                    // still recorded, so uses of the label aren't reported as undefined
+                    self.imports.push(definition);
+                    continue;
+                }
+                (OperationKind::Directive(Directive::Blkw), [operand]) => {
+                    self.number(line, operand, 0..=0xffff).unwrap_or(0) as u32
+                }
+                (OperationKind::Directive(Directive::Stringz), [operand]) => match &operand.kind {
+                    OperandKind::Str(text) => text.chars().count() as u32 + 1,
+                    _ => {
+                        self.error(line, operand.span.clone(), "expected a string");
+                        continue;
+                    }
+                },
+                _ => 1,
+            };
+
+            if address + size > 0x10000 {
+                self.error(
+                    line,
+                    operation.span,
+                    "the program runs past the end of memory (xFFFF)",
+                );
+                break;
+            }
+            located.push(Located {
+                line,
+                address: address as u16,
+                operation,
+            });
+            address += size;
+        }
+
+        if origin.is_none() && !self.relocatable {
+            self.report(
+                Severity::Error,
+                0,
+                0..0,
+                "the program has no .ORIG",
+                Some("start the program with `.ORIG x3000`".to_string()),
+            );
+        }
+
+        (origin, located)
+    }
+
+    /// Checks `.IMPORT`s and `.EXPORT`s against the labels the program defines.
+    fn check_linkage(&mut self) {
+        let mut errors = Vec::new();
+        for (index, import) in self.imports.iter().enumerate() {
+            if self.imports[..index]
+                .iter()
+                .any(|earlier| earlier.label == import.label)
+            {
+                errors.push((
+                    import.line,
+                    import.span.clone(),
+                    format!("`{}` is already imported", import.label),
+                ));
+            } else if let Some(address) = self.symbols.address_of(&import.label) {
+                errors.push((
+                    import.line,
+                    import.span.clone(),
+                    format!(
+                        "`{}` is imported but also defined here, at x{address:04X}",
+                        import.label
+                    ),
+                ));
+            }
+        }
+        for export in &self.exports {
+            if self.symbols.address_of(&export.label).is_none() {
+                errors.push((
+                    export.line,
+                    export.span.clone(),
+                    format!("can't export `{}`, it isn't defined", export.label),
+                ));
+            }
+            // other modules use it
+            self.used_labels.insert(export.label.clone());
+        }
+
+        for (line, span, message) in errors {
+            self.error(line, span, message);
+        }
+    }
+
+    fn check_arity(&mut self, line: usize, operation: &Operation) -> bool {
+        let arity = match operation.kind {
+            OperationKind::Instruction(mnemonic) => mnemonic.arity(),
+            OperationKind::Directive(directive) => directive.arity(),
+        };
+        let found = operation.operands.len();
+        if found == arity {
+            return true;
+        }
+
+        let span = match operation.operands.get(arity) {
+            Some(extra) => extra.span.start..operation.operands[found - 1].span.end,
+            None => operation.span.clone(),
+        };
+        let plural = if arity == 1 { "" } else { "s" };
+        self.error(
+            line,
+            span,
+            format!(
+                "{} takes {arity} operand{plural}, found {found}",
+                operation.name
+            ),
+        );
+
+        false
+    }
+
+    /// Second pass: appends the words of one statement.
+    /// Words that can't be encoded are reported and left as 0.
+    fn encode(&mut self, statement: &Located, words: &mut Vec<u16>) {
+        let Located {
+            line,
+            address,
+            operation,
+        } = statement;
+        let line = *line;
+        let operands = &operation.operands[..];
+
+        let mnemonic = match operation.kind {
+            OperationKind::Instruction(mnemonic) => mnemonic,
+            OperationKind::Directive(directive) => {
+                match (directive, operands) {
+                    (Directive::Fill, [operand]) => {
+                        let value = match &operand.kind {
+                            OperandKind::Label(label) => match self.import(operand) {
+                                Some(import) => {
+                                    self.relocate(*address, RelocationKind::Address, Some(import));
+                                    Some(0)
+                                }
+                                None => {
+                                    let value = self.label(line, operand);
+                                    if let Some(value) = value {
+                                        self.target = Some((label.clone(), value));
+                                        self.relocate(*address, RelocationKind::Address, None);
+                                    }
+                                    value
+                                }
+                            },
+                            _ => self.fill_value(line, operand),
+                        };
+                        words.push(value.unwrap_or(0));
+                    }
+                    (Directive::Blkw, [operand]) => {
+                        // the count was checked, and reported, in the first pass
+                        let count = match operand.kind {
+                            OperandKind::Number(count @ 0..=0xffff) => count,
+                            _ => 0,
+                        };
+                        words.extend(std::iter::repeat_n(0, count as usize));
+                    }
+                    (Directive::Stringz, [operand]) => {
+                        if let OperandKind::Str(text) = &operand.kind {
+                            words.extend(text.chars().map(|c| c as u16));
+                            words.push(0);
+                        }
+                    }
+                    _ => {}
+                }
+                return;
+            }
+        };
+
+        let name = &operation.name;
+        let mut encode = || -> Option<Instruction> {
+            let instruction = match (mnemonic, operands) {
+                (Mnemonic::Add | Mnemonic::And, [dr, sr1, operand]) => {
+                    let (dr, sr1) = (self.register(line, dr), self.register(line, sr1));
+                    let operand = match operand.kind {
+                        OperandKind::Register(sr2) => Some(instruction::Operand::Register(sr2)),
+                        _ => self
+                            .immediate(line, operand, 5, name)
+                            .map(instruction::Operand::Immediate),
+                    };
+                    let (dr, sr1, operand) = (dr?, sr1?, operand?);
+                    match mnemonic {
+                        Mnemonic::Add => Instruction::Add {
+                            dr,
+                            sr1,
+                            operand,
+                            unused: 0,
+                        },
+                        _ => Instruction::And {
+                            dr,
+                            sr1,
+                            operand,
+                            unused: 0,
+                        },
+                    }
+                }
+                (Mnemonic::Not, [dr, sr]) => {
+                    let (dr, sr) = (self.register(line, dr), self.register(line, sr));
+                    Instruction::Not {
+                        dr: dr?,
+                        sr: sr?,
+                        unused: 0,
+                    }
+                }
+                (Mnemonic::Br { n, z, p }, [target]) => Instruction::Br {
+                    n,
+                    z,
+                    p,
+                    offset: self.pc_offset(line, *address, target, 9, name)?,
+                },
+                (Mnemonic::Jmp, [base]) => Instruction::Jmp {
+                    base: self.register(line, base)?,
+                    unused: 0,
+                },
+                (Mnemonic::Ret, []) => Instruction::Jmp { base: 7, unused: 0 },
+                (Mnemonic::Jsr, [target]) => Instruction::Jsr {
+                    offset: self.pc_offset(line, *address, target, 11, name)?,
+                },
+                (Mnemonic::Jsrr, [base]) => Instruction::Jsrr {
+                    base: self.register(line, base)?,
+                    unused: 0,
+                },
+                (
+                    Mnemonic::Ld | Mnemonic::Ldi | Mnemonic::Lea | Mnemonic::St | Mnemonic::Sti,
+                    [register, target],
+                ) => {
+                    let register = self.register(line, register);
+                    let offset = self.pc_offset(line, *address, target, 9, name);
+                    let (register, offset) = (register?, offset?);
+                    match mnemonic {
+                        Mnemonic::Ld => Instruction::Ld {
+                            dr: register,
+                            offset,
+                        },
+                        Mnemonic::Ldi => Instruction::Ldi {
+                            dr: register,
+                            offset,
+                        },
+                        Mnemonic::Lea => Instruction::Lea {
+                            dr: register,
+                            offset,
+                        },
+                        Mnemonic::St => Instruction::St {
+                            sr: register,
+                            offset,
+                        },
+                        _ => Instruction::Sti {
+                            sr: register,
+                            offset,
+                        },
+                    }
+                }
+                (Mnemonic::Ldr | Mnemonic::Str, [register, base, offset]) => {
+                    let (register, base) =
+                        (self.register(line, register), self.register(line, base));
+                    let offset = self.immediate(line, offset, 6, name);
+                    let (register, base, offset) = (register?, base?, offset?);
+                    match mnemonic {
+                        Mnemonic::Ldr => Instruction::Ldr {
+                            dr: register,
+                            base,
+                            offset,
+                        },
+                        _ => Instruction::Str {
+                            sr: register,
+                            base,
+                            offset,
+                        },
+                    }
+                }
+                (Mnemonic::Trap, [vector]) => trap(self.number(line, vector, 0..=0xff)? as u8),
+                (Mnemonic::Rti, []) => Instruction::Rti { unused: 0 },
+                (Mnemonic::Getc, []) => trap(0x20),
+                (Mnemonic::Out, []) => trap(0x21),
+                (Mnemonic::Puts, []) => trap(0x22),
+                (Mnemonic::In, []) => trap(0x23),
+                (Mnemonic::Putsp, []) => trap(0x24),
+                (Mnemonic::Halt, []) => trap(0x25),
+                // the operand count was checked, and reported, in the first pass
+                _ => return None,
+            };
+
+            Some(instruction)
+        };
+
+        words.push(encode().map_or(0, Instruction::encode));
+    }
+
+    fn register(&mut self, line: usize, operand: &Operand) -> Option<u16> {
+        match operand.kind {
+            OperandKind::Register(index) => Some(index),
+            _ => {
+                self.error(line, operand.span.clone(), "expected a register (R0 - R7)");
+                None
+            }
+        }
+    }
+
+    /// The value of a number operand, which has to be within `range`.
+    fn number(
+        &mut self,
+        line: usize,
+        operand: &Operand,
+        range: RangeInclusive<i32>,
+    ) -> Option<i32> {
+        match operand.kind {
+            OperandKind::Number(value) if range.contains(&value) => Some(value),
+            OperandKind::Number(value) => {
+                self.error(
+                    line,
+                    operand.span.clone(),
+                    format!(
+                        "{value} is out of range, expected {} to {}",
+                        range.start(),
+                        range.end()
+                    ),
+                );
+                None
+            }
+            _ => {
+                self.error(line, operand.span.clone(), "expected a number");
+                None
+            }
+        }
+    }
+
+    /// A `.FILL` number, truncated to 16 bits with a warning if it doesn't fit.
+    fn fill_value(&mut self, line: usize, operand: &Operand) -> Option<u16> {
+        let value = self.number(line, operand, i32::MIN..=i32::MAX)?;
+        let word = value as u16;
+
+        if !(-0x8000..=0xffff).contains(&value) {
+            self.report(
+                Severity::Warning,
+                line,
+                operand.span.clone(),
+                format!("{value} doesn't fit in 16 bits and is truncated to x{word:04X}"),
+                Some(".FILL takes values from -32768 to 65535 (xFFFF)".to_string()),
+            );
+        }
+
+        Some(word)
+    }
+
+    fn label(&mut self, line: usize, operand: &Operand) -> Option<u16> {
+        let OperandKind::Label(name) = &operand.kind else {
+            self.error(line, operand.span.clone(), "expected a label");
+            return None;
+        };
+        self.used_labels.insert(name.clone());
+
+        let address = self.symbols.address_of(name);
+        if address.is_none() {
+            let case_insensitive = self
+                .symbols
+                .iter()
+                .find(|(label, _)| label.eq_ignore_ascii_case(name))
+                .map(|(label, _)| format!("labels are case sensitive, did you mean `{label}`?"));
+            let help = case_insensitive.or_else(|| {
+                self.symbols
+                    .iter()
+                    .map(|(label, _)| (edit_distance(label, name), label))
+                    .filter(|(distance, _)| *distance <= 2)
+                    .min()
+                    .map(|(_, label)| format!("did you mean `{label}`?"))
+            });
+            self.report(
+                Severity::Error,
+                line,
+                operand.span.clone(),
+                format!("undefined label `{name}`"),
+                help,
+            );
+        }
+
+        address
+    }
+
+    /// A signed immediate or base register offset of `bits` bits.
+    fn immediate(&mut self, line: usize, operand: &Operand, bits: u32, name: &str) -> Option<i16> {
+        let value = self.number(line, operand, i32::MIN..=i32::MAX)?;
+        let (min, max) = signed_range(bits);
+
+        if !(min..=max).contains(&value) {
+            let help = match bits {
+                5 => format!(
+                    "{name} takes immediates from {min} to {max}; put larger values in a register first"
+                ),
+                _ => format!("{name} takes offsets from {min} to {max}"),
+            };
+            self.report(
+                Severity::Error,
+                line,
+                operand.span.clone(),
+                format!("#{value} doesn't fit in {bits} bits"),
+                Some(help),
+            );
+            return None;
+        }
+
+        Some(value as i16)
+    }
+
+    /// A PCoffset field: labels are relative to the incremented PC, numbers are the offset itself.
+    fn pc_offset(
+        &mut self,
+        line: usize,
+        address: u16,
+        operand: &Operand,
+        bits: u32,
+        name: &str,
+    ) -> Option<i16> {
+        let (min, max) = signed_range(bits);
+        let help = Some(format!(
+            "{name} reaches from {min} to {max} words away from the instruction after it (PCoffset{bits})"
+        ));
+
+        let offset = match &operand.kind {
+            OperandKind::Label(label) => {
+                if let Some(import) = self.import(operand) {
+                    let kind = match bits {
+                        11 => RelocationKind::PcOffset11,
+                        _ => RelocationKind::PcOffset9,
+                    };
+                    self.relocate(address, kind, Some(import));
+                    return Some(0);
+                }
+                let target = self.label(line, operand)?;
+                self.target = Some((label.clone(), target));
+                let offset = target as i32 - (address as i32 + 1);
+                if !(min..=max).contains(&offset) {
+                    self.report(
+                        Severity::Error,
+                        line,
+                        operand.span.clone(),
+                        format!("`{label}` is out of range of {name}, it's {offset} words away"),
+                        help,
+                    );
+                    return None;
+                }
+                offset
+            }
+            _ => {
+                let offset = self.number(line, operand, i32::MIN..=i32::MAX)?;
+                if !(min..=max).contains(&offset) {
+                    self.report(
+                        Severity::Error,
+                        line,
+                        operand.span.clone(),
+                        format!("offset {offset} doesn't fit in {bits} bits"),
+                        help,
+                    );
+                    return None;
+                }
+                offset
+            }
+        };
+
+        Some(offset as i16)
+    }
+
+    /// The index of the import `operand` refers to, if it's an imported label.
+    fn import(&mut self, operand: &Operand) -> Option<u16> {
+        let OperandKind::Label(name) = &operand.kind else {
+            return None;
+        };
+        let index = self
+            .imports
+            .iter()
+            .position(|import| &import.label == name)?;
+        self.used_labels.insert(name.clone());
+
+        Some(index as u16)
+    }
+
+    /// Records that the linker has to patch the word at `address`.
+    fn relocate(&mut self, address: u16, kind: RelocationKind, import: Option<u16>) {
+        self.relocations.push(Relocation {
+            offset: address.wrapping_sub(self.origin),
+            kind,
+            import,
+        });
+    }
+
+    fn check_unused_labels(&mut self) {
+        let unused_labels = self
+            .definitions
+            .iter()
+            .map(|definition| (definition, "label"));
+        let unused_imports = self.imports.iter().map(|import| (import, "imported label"));
+        let unused: Vec<(usize, Range<usize>, String)> = unused_labels
+            .chain(unused_imports)
+            .filter(|(definition, _)| !self.used_labels.contains(&definition.label))
+            .map(|(definition, what)| {
+                (
+                    definition.line,
+                    definition.span.clone(),
+                    format!("{what} `{}` is never used", definition.label),
+                )
+            })
+            .collect();
+
+        for (line, span, message) in unused {
+            self.report(Severity::Warning, line, span, message, None);
+        }
+    }
+}
+
+/// Levenshtein distance, to suggest the label a typo was meant to be.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// What TRAP and its aliases, like HALT, assemble to.
+fn trap(vector: u8) -> Instruction {
+    Instruction::Trap { vector, unused: 0 }
+}
+
+fn signed_range(bits: u32) -> (i32, i32) {
+    (-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hardware::os;
+
+    #[test]
+    fn test_assemble() {
+        let source = "        .ORIG x3000
+        LEA R0, HELLO
+LOOP    ADD R1, R1, #-1
+        BRp LOOP
+        AND R2, R2, R3
+        NOT R3, R4
+        LDR R5, R6, #-32
+        STR R5, R6, #31
+        JSR SUB
+        JSRR R2
+        TRAP x21
+        HALT
+SUB     RET
+HELLO   .STRINGZ \"hi\"
+        .FILL xFFFF
+        .FILL LOOP
+        .BLKW 2
+        .END
+        this isn't assembled";
+
+        let assembly = assemble(source).unwrap();
+
+        assert_eq!(assembly.image.origin, 0x3000);
+        assert_eq!(
+            assembly.image.words,
+            [
+                0xe00b, 0x127f, 0x03fe, 0x5483, 0x973f, 0x6ba0, 0x7b9f, 0x4803, 0x4080, 0xf021,
+                0xf025, 0xc1c0, 0x0068, 0x0069, 0x0000, 0xffff, 0x3001, 0x0000, 0x0000
+            ]
+        );
+        assert_eq!(assembly.symbols.address_of("LOOP"), Some(0x3001));
+        assert_eq!(assembly.symbols.address_of("HELLO"), Some(0x300c));
+        assert!(assembly.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_os_image() {
+        let assembly = assemble(include_str!("../hardware/os/lc3os.asm")).unwrap();
+
+        assert_eq!(assembly.image, Image::from_bytes(os::IMAGE).unwrap());
+        assert_eq!(assembly.image.to_bytes(), os::IMAGE);
+        assert_eq!(assembly.symbols.address_of("OS_START"), Some(os::OS_START));
+        assert_eq!(assembly.symbols.address_of("USER_PC"), Some(os::USER_PC));
+    }
+
+    #[test]
+    fn test_listing() {
+        let source = "        .ORIG x3000
+LOOP    ADD R1, R1, #-1
+        BRp LOOP    ; again
+        .STRINGZ \"a\"
+        .BLKW 3
+        .END
+        not listed";
+
+        assert_eq!(
+            assemble(source).unwrap().listing.to_lst_file(),
+            "; <source>
+Address  Hex    Binary                 Target            Line  Source
+                                                            1          .ORIG x3000
+x3000    x127F  0001 001 001 1 11111                        2  LOOP    ADD R1, R1, #-1
+x3001    x03FE  0000 001 111111110     LOOP (x3000)         3          BRp LOOP    ; again
+x3002    x0061  0000 0000 0110 0001                         4          .STRINGZ \"a\"
+x3003    x0000  0000 0000 0000 0000
+x3004    x0000  0000 0000 0000 0000                         5          .BLKW 3
+...      x0000  (2 more words up to x3006)
+                                                            6          .END
+"
+        );
+    }
+
+    fn messages(source: &str) -> Vec<(Severity, usize, Range<usize>, String)> {
+        let diagnostics = match assemble(source) {
+            Ok(assembly) => assembly.warnings,
+            Err(diagnostics) => diagnostics,
+        };
+
+        diagnostics
+            .into_iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.severity,
+                    diagnostic.line,
+                    diagnostic.span,
+                    diagnostic.message,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_all_errors_reported() {
+        let source = ".ORIG x3000
+        ADD R1, R1, #16
+        BRz FAR
+        LD R0, NOWHERE
+A       HALT
+A       HALT
+        ADD R1, R1
+        FOO R1
+        .BLKW 256
+        .BLKW #-1
+FAR     HALT
+LOOP:   ADD R1, R1, #1
+        BRp LOOP
+        brNZP
+        .END";
+
+        assert_eq!(
+            messages(source),
+            [
+                (
+                    Severity::Error,
+                    2,
+                    20..23,
+                    "#16 doesn't fit in 5 bits".to_string()
+                ),
+                (
+                    Severity::Error,
+                    3,
+                    12..15,
+                    "`FAR` is out of range of BRz, it's 260 words away".to_string()
+                ),
+                (
+                    Severity::Error,
+                    4,
+                    15..22,
+                    "undefined label `NOWHERE`".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    5,
+                    0..1,
+                    "label `A` is never used".to_string()
+                ),
+                (
+                    Severity::Error,
+                    6,
+                    0..1,
+                    "label `A` is already defined, at x3003".to_string()
+                ),
+                (
+                    Severity::Error,
+                    7,
+                    8..11,
+                    "ADD takes 3 operands, found 2".to_string()
+                ),
+                (
+                    Severity::Error,
+                    8,
+                    8..11,
+                    "unknown instruction `FOO`".to_string()
+                ),
+                (
+                    Severity::Error,
+                    10,
+                    14..17,
+                    "-1 is out of range, expected 0 to 65535".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    12,
+                    0..5,
+                    "labels don't take a colon".to_string()
+                ),
+                (
+                    Severity::Error,
+                    14,
+                    8..13,
+                    "BRnzp takes 1 operand, found 0".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_warnings() {
+        let source = ".ORIG x3000
+        LD R0, BIG
+        HALT
+BIG     .FILL #70000
+UNUSED  .FILL x-1
+        .END";
+
+        let assembly = assemble(source).unwrap();
+        assert_eq!(assembly.image.words, [0x2001, 0xf025, 0x1170, 0xffff]);
+        assert_eq!(
+            messages(source),
+            [
+                (
+                    Severity::Warning,
+                    4,
+                    14..20,
+                    "70000 doesn't fit in 16 bits and is truncated to x1170".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    5,
+                    0..6,
+                    "label `UNUSED` is never used".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_label_suggestions() {
+        let help = |source: &str| match assemble(source) {
+            Err(diagnostics) => diagnostics[0].help.clone(),
+            Ok(_) => panic!("{source} assembled"),
+        };
+
+        assert_eq!(
+            help(".ORIG x3000\nBR LOOP\nLOOOP HALT"),
+            Some("did you mean `LOOOP`?".to_string())
+        );
+        assert_eq!(
+            help(".ORIG x3000\nBR loop\nLoop HALT"),
+            Some("labels are case sensitive, did you mean `Loop`?".to_string())
+        );
+        assert_eq!(help(".ORIG x3000\nBR DONE\nLOOP HALT"), None);
+    }
+
+    #[test]
+    fn test_preprocessor() {
+        let dir = std::env::temp_dir().join(format!("lc3-include-{}", std::process::id()));
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        fs::write(
+            dir.join("lib/stack.asm"),
+            ".MACRO PUSH REG\n\tADD R6, R6, #-1\n\tSTR REG, R6, #0\n.ENDM\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("main.asm"),
+            ".INCLUDE \"lib/stack.asm\"\n.DEFINE ZERO #0\n.ORIG x3000\n\tPUSH R7\n\tPUSH ZERO\n.END\n",
+        )
+        .unwrap();
+
+        let diagnostics = assemble_file(dir.join("main.asm")).unwrap().unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // the error is in the macro body, where the argument ended up
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.message, "expected a register (R0 - R7)");
+        assert!(diagnostic.file.ends_with("lib/stack.asm"));
+        assert_eq!(diagnostic.line, 3);
+        assert_eq!(diagnostic.source_line, "\tSTR #0, R6, #0");
+        assert!(diagnostic
+            .note
+            .as_ref()
+            .is_some_and(|note| note.starts_with("in macro `PUSH`, used at ")
+                && note.ends_with("main.asm:5")));
+
+        let assembly = assemble(
+            ".MACRO PUSH REG\nADD R6, R6, #-1\nSTR REG, R6, #0\n.ENDM\n.ORIG x3000\nPUSH R7\nHALT\n.END",
+        )
+        .unwrap();
+        assert_eq!(assembly.image.words, [0x1dbf, 0x7f80, 0xf025]);
+    }
+
+    #[test]
+    fn test_module() {
+        let source = "        .IMPORT PRINT
+        .EXPORT MAIN
+MAIN    JSR PRINT
+        BR MAIN
+DATA    .FILL DATA
+        .FILL PRINT";
+
+        let module = assemble_module(source).unwrap().module;
+        assert_eq!(module.origin, None);
+        assert_eq!(module.words, [0x4800, 0x0ffe, 0x0002, 0x0000]);
+        assert_eq!(module.imports, ["PRINT"]);
+        assert_eq!(
+            module.relocations,
+            [
+                Relocation {
+                    offset: 0,
+                    kind: RelocationKind::PcOffset11,
+                    import: Some(0),
+                },
+                Relocation {
+                    offset: 2,
+                    kind: RelocationKind::Address,
+                    import: None,
+                },
+                Relocation {
+                    offset: 3,
+                    kind: RelocationKind::Address,
+                    import: Some(0),
+                },
+            ]
+        );
+        assert!(module.symbols.contains(&ModuleSymbol {
+            name: "MAIN".to_string(),
+            offset: 0,
+            exported: true,
+        }));
+
+        assert_eq!(
+            messages(".ORIG x3000\n.IMPORT PRINT\nJSR PRINT"),
+            [(
+                Severity::Error,
+                2,
+                0..7,
+                ".IMPORT can only be used in a module".to_string()
+            )]
+        );
+        let errors = assemble_module(".EXPORT NOWHERE\n.IMPORT X\nX BR X\n.IMPORT Y\n.ORIG x3000")
+            .unwrap_err()
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                "can't export `NOWHERE`, it isn't defined",
+                "`X` is imported but also defined here, at x0000",
+                "imported label `Y` is never used",
+                ".ORIG has to come before the first instruction or data",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_orig() {
+        assert_eq!(
+            messages("HALT"),
+            [(
+                Severity::Error,
+                1,
+                0..4,
+                "the program has to start with .ORIG".to_string()
+            )]
+        );
+    }
+}
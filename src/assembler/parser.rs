@@ -0,0 +1,408 @@
+use std::ops::Range;
+
+use super::lexer::{Token, TokenKind};
+
+/// The instructions and trap aliases the assembler knows, as written in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mnemonic {
+    Add,
+    And,
+    Not,
+    Br { n: bool, z: bool, p: bool },
+    Jmp,
+    Ret,
+    Jsr,
+    Jsrr,
+    Ld,
+    Ldi,
+    Ldr,
+    Lea,
+    St,
+    Sti,
+    Str,
+    Trap,
+    Rti,
+    Getc,
+    Out,
+    Puts,
+    In,
+    Putsp,
+    Halt,
+}
+
+impl Mnemonic {
+    pub fn parse(word: &str) -> Option<Self> {
+        let mnemonic = match word.to_ascii_uppercase().as_str() {
+            "ADD" => Mnemonic::Add,
+            "AND" => Mnemonic::And,
+            "NOT" => Mnemonic::Not,
+            "JMP" => Mnemonic::Jmp,
+            "RET" => Mnemonic::Ret,
+            "JSR" => Mnemonic::Jsr,
+            "JSRR" => Mnemonic::Jsrr,
+            "LD" => Mnemonic::Ld,
+            "LDI" => Mnemonic::Ldi,
+            "LDR" => Mnemonic::Ldr,
+            "LEA" => Mnemonic::Lea,
+            "ST" => Mnemonic::St,
+            "STI" => Mnemonic::Sti,
+            "STR" => Mnemonic::Str,
+            "TRAP" => Mnemonic::Trap,
+            "RTI" => Mnemonic::Rti,
+            "GETC" => Mnemonic::Getc,
+            "OUT" => Mnemonic::Out,
+            "PUTS" => Mnemonic::Puts,
+            "IN" => Mnemonic::In,
+            "PUTSP" => Mnemonic::Putsp,
+            "HALT" => Mnemonic::Halt,
+            "BR" => Mnemonic::Br {
+                n: true,
+                z: true,
+                p: true,
+            },
+            upper => return Self::parse_br(upper),
+        };
+
+        Some(mnemonic)
+    }
+
+    /// `BRn`, `BRzp`, ...: the condition codes have to be in `nzp` order.
+    fn parse_br(upper: &str) -> Option<Self> {
+        let mut conditions = upper.strip_prefix("BR")?;
+        let mut flag = |c: char| match conditions.strip_prefix(c) {
+            Some(rest) => {
+                conditions = rest;
+                true
+            }
+            None => false,
+        };
+        let (n, z, p) = (flag('N'), flag('Z'), flag('P'));
+
+        conditions.is_empty().then_some(Mnemonic::Br { n, z, p })
+    }
+
+    /// Number of operands in the source.
+    pub fn arity(self) -> usize {
+        match self {
+            Mnemonic::Add | Mnemonic::And | Mnemonic::Ldr | Mnemonic::Str => 3,
+            Mnemonic::Not
+            | Mnemonic::Ld
+            | Mnemonic::Ldi
+            | Mnemonic::Lea
+            | Mnemonic::St
+            | Mnemonic::Sti => 2,
+            Mnemonic::Br { .. }
+            | Mnemonic::Jmp
+            | Mnemonic::Jsr
+            | Mnemonic::Jsrr
+            | Mnemonic::Trap => 1,
+            Mnemonic::Ret
+            | Mnemonic::Rti
+            | Mnemonic::Getc
+            | Mnemonic::Out
+            | Mnemonic::Puts
+            | Mnemonic::In
+            | Mnemonic::Putsp
+            | Mnemonic::Halt => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Directive {
+    Orig,
+    Fill,
+    Blkw,
+    Stringz,
+    End,
+    /// Uses a label defined in another module.
+    Import,
+    /// Lets other modules use a label.
+    Export,
+}
+
+impl Directive {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "ORIG" => Some(Directive::Orig),
+            "FILL" => Some(Directive::Fill),
+            "BLKW" => Some(Directive::Blkw),
+            "STRINGZ" => Some(Directive::Stringz),
+            "END" => Some(Directive::End),
+            "IMPORT" => Some(Directive::Import),
+            "EXPORT" => Some(Directive::Export),
+            _ => None,
+        }
+    }
+
+    pub fn arity(self) -> usize {
+        match self {
+            Directive::End => 0,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationKind {
+    Instruction(Mnemonic),
+    Directive(Directive),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OperandKind {
+    Register(u16),
+    Number(i32),
+    Label(String),
+    Str(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operand {
+    pub kind: OperandKind,
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operation {
+    pub kind: OperationKind,
+    /// The mnemonic or pseudo-op in upper case, except for the conditions of BR, e.g. `ADD`, `BRnz`
+    /// or `.FILL`.
+    pub name: String,
+    /// Where the mnemonic or pseudo-op is in the line.
+    pub span: Range<usize>,
+    pub operands: Vec<Operand>,
+}
+
+/// One line of assembly: `[label] [operation [operand {, operand}]]`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Statement {
+    pub label: Option<(String, Range<usize>)>,
+    pub operation: Option<Operation>,
+}
+
+/// A syntax error and the part of the line it's about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+impl ParseError {
+    fn new(message: impl Into<String>, span: Range<usize>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+pub fn parse_statement(tokens: &[Token]) -> Result<Statement, ParseError> {
+    let mut statement = Statement::default();
+    let mut tokens = tokens.iter().peekable();
+
+    if let Some(Token {
+        kind: TokenKind::Word(word),
+        span,
+    }) = tokens.peek()
+    {
+        if Mnemonic::parse(word).is_none() {
+            if register(word).is_some() {
+                return Err(ParseError::new(
+                    format!("`{word}` is a register and can't be used as a label"),
+                    span.clone(),
+                ));
+            }
+            statement.label = Some((word.clone(), span.clone()));
+            tokens.next();
+
+            // `FOO R1, R2` is a misspelled instruction rather than a label
+            let operand_follows = match tokens.peek().map(|token| &token.kind) {
+                Some(TokenKind::Word(next)) => register(next).is_some(),
+                Some(TokenKind::Number(_) | TokenKind::Str(_) | TokenKind::Comma) => true,
+                _ => false,
+            };
+            if operand_follows {
+                return Err(ParseError::new(
+                    format!("unknown instruction `{word}`"),
+                    span.clone(),
+                ));
+            }
+        }
+    }
+
+    let Some(token) = tokens.next() else {
+        return Ok(statement);
+    };
+    let (kind, name) = match &token.kind {
+        TokenKind::Word(word) => match Mnemonic::parse(word) {
+            Some(mnemonic) => {
+                let name = match mnemonic {
+                    Mnemonic::Br { .. } => format!("BR{}", word[2..].to_ascii_lowercase()),
+                    _ => word.to_ascii_uppercase(),
+                };
+                (OperationKind::Instruction(mnemonic), name)
+            }
+            None => {
+                return Err(ParseError::new(
+                    format!("unknown instruction `{word}`"),
+                    token.span.clone(),
+                ))
+            }
+        },
+        TokenKind::Directive(name) => match Directive::parse(name) {
+            Some(directive) => (OperationKind::Directive(directive), format!(".{name}")),
+            None => {
+                return Err(ParseError::new(
+                    format!("unknown pseudo-op `.{name}`"),
+                    token.span.clone(),
+                ))
+            }
+        },
+        _ => {
+            return Err(ParseError::new(
+                "expected an instruction or a pseudo-op",
+                token.span.clone(),
+            ))
+        }
+    };
+
+    let mut operands = Vec::new();
+    while let Some(token) = tokens.next() {
+        let kind = match &token.kind {
+            TokenKind::Word(word) => match register(word) {
+                Some(index) => OperandKind::Register(index),
+                None => OperandKind::Label(word.clone()),
+            },
+            TokenKind::Number(number) => OperandKind::Number(*number),
+            TokenKind::Str(text) => OperandKind::Str(text.clone()),
+            TokenKind::Directive(_) | TokenKind::Comma => {
+                return Err(ParseError::new("expected an operand", token.span.clone()))
+            }
+        };
+        operands.push(Operand {
+            kind,
+            span: token.span.clone(),
+        });
+
+        match tokens.next() {
+            None => break,
+            Some(Token {
+                kind: TokenKind::Comma,
+                span,
+            }) if tokens.peek().is_none() => {
+                return Err(ParseError::new(
+                    "expected an operand after `,`",
+                    span.clone(),
+                ))
+            }
+            Some(Token {
+                kind: TokenKind::Comma,
+                ..
+            }) => {}
+            Some(token) => return Err(ParseError::new("expected `,`", token.span.clone())),
+        }
+    }
+
+    statement.operation = Some(Operation {
+        kind,
+        name,
+        span: token.span.clone(),
+        operands,
+    });
+
+    Ok(statement)
+}
+
+/// `R0` - `R7`, in either case.
+pub fn register(word: &str) -> Option<u16> {
+    match word.as_bytes() {
+        [b'R' | b'r', digit @ b'0'..=b'7'] => Some((digit - b'0') as u16),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::lexer::tokenize;
+    use super::*;
+
+    fn parse(line: &str) -> Result<Statement, ParseError> {
+        parse_statement(&tokenize(line).unwrap())
+    }
+
+    #[test]
+    fn test_mnemonics() {
+        assert_eq!(Mnemonic::parse("add"), Some(Mnemonic::Add));
+        assert_eq!(
+            Mnemonic::parse("BRnp"),
+            Some(Mnemonic::Br {
+                n: true,
+                z: false,
+                p: true
+            })
+        );
+        assert_eq!(
+            Mnemonic::parse("BR"),
+            Some(Mnemonic::Br {
+                n: true,
+                z: true,
+                p: true
+            })
+        );
+        assert_eq!(Mnemonic::parse("BRpn"), None);
+        assert_eq!(Mnemonic::parse("BRANCH"), None);
+    }
+
+    #[test]
+    fn test_parse_statement() {
+        let statement = parse("LOOP ADD R1, R1, #-1").unwrap();
+        assert_eq!(statement.label, Some(("LOOP".to_string(), 0..4)));
+
+        let operation = statement.operation.unwrap();
+        assert_eq!(operation.kind, OperationKind::Instruction(Mnemonic::Add));
+        assert_eq!(operation.span, 5..8);
+        assert_eq!(
+            operation
+                .operands
+                .iter()
+                .map(|operand| &operand.kind)
+                .collect::<Vec<_>>(),
+            [
+                &OperandKind::Register(1),
+                &OperandKind::Register(1),
+                &OperandKind::Number(-1)
+            ]
+        );
+
+        let statement = parse("DONE").unwrap();
+        assert_eq!(statement.label, Some(("DONE".to_string(), 0..4)));
+        assert_eq!(statement.operation, None);
+
+        assert_eq!(parse("  ; just a comment").unwrap(), Statement::default());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse("LOOP FOO R1").unwrap_err(),
+            ParseError::new("unknown instruction `FOO`", 5..8)
+        );
+        assert_eq!(
+            parse("  FOO R1").unwrap_err(),
+            ParseError::new("unknown instruction `FOO`", 2..5)
+        );
+        assert_eq!(
+            parse("ADD R1 R1, #1").unwrap_err(),
+            ParseError::new("expected `,`", 7..9)
+        );
+        assert_eq!(
+            parse("ADD R1, R1,").unwrap_err(),
+            ParseError::new("expected an operand after `,`", 10..11)
+        );
+        assert_eq!(
+            parse(".ORIGIN x3000").unwrap_err(),
+            ParseError::new("unknown pseudo-op `.ORIGIN`", 0..7)
+        );
+    }
+}
@@ -0,0 +1,606 @@
+use std::{
+    collections::HashMap,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use super::{
+    diagnostic::{Diagnostic, Severity},
+    lexer::{tokenize, Token, TokenKind},
+    parser::{register, Mnemonic},
+};
+
+/// How deeply macros may call macros, to stop recursive macros.
+const MAX_EXPANSION_DEPTH: usize = 64;
+
+/// A line of the program after preprocessing, and where it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLine {
+    /// The file the line is in, as given to the assembler or in `.INCLUDE`.
+    pub file: Rc<str>,
+    /// 1-based line number in `file`.
+    pub number: usize,
+    /// The line with `.DEFINE` constants and macro parameters substituted.
+    pub text: String,
+    /// For lines expanded from a macro, which macro and where it was used.
+    pub expansion: Option<Rc<str>>,
+}
+
+impl SourceLine {
+    /// The lines of a whole file.
+    pub fn split(file: &str, source: &str) -> Vec<SourceLine> {
+        let file: Rc<str> = file.into();
+
+        source
+            .lines()
+            .enumerate()
+            .map(|(index, text)| SourceLine {
+                file: file.clone(),
+                number: index + 1,
+                text: text.to_string(),
+                expansion: None,
+            })
+            .collect()
+    }
+}
+
+struct Macro {
+    params: Vec<String>,
+    /// Labels defined in the body, renamed on every expansion so it can be used more than once.
+    labels: Vec<String>,
+    body: Vec<SourceLine>,
+}
+
+/// One level of `.IFDEF`/`.IFNDEF`.
+struct Condition {
+    /// Whether the lines are assembled in the current branch.
+    active: bool,
+    /// Whether the enclosing lines are assembled at all.
+    parent_active: bool,
+    seen_else: bool,
+    line: SourceLine,
+    span: Range<usize>,
+}
+
+/// Expands `.INCLUDE`, `.DEFINE`, `.MACRO`/`.ENDM` and `.IFDEF`/`.IFNDEF`/`.ELSE`/`.ENDIF`,
+/// leaving only lines for the assembler proper.
+///
+/// ```text
+///         .INCLUDE "lib/stack.asm"    ; relative to the including file
+///         .DEFINE STACK_SIZE #32      ; replaces the word STACK_SIZE from here on
+///         .MACRO PUSH REG             ; parameters are replaced by the arguments
+///         ADD R6, R6, #-1
+///         STR REG, R6, #0
+///         .ENDM
+///         PUSH R7
+///         .IFDEF DEBUG                ; assembled only if DEBUG was defined
+///         PUTS
+///         .ENDIF
+/// ```
+pub struct Preprocessor {
+    defines: HashMap<String, String>,
+    /// By upper-case name, as macros are used like instructions.
+    macros: HashMap<String, Macro>,
+    /// Canonical paths of the files being included, innermost last.
+    includes: Vec<PathBuf>,
+    expansions: usize,
+    depth: usize,
+    /// Set once a macro is nested too deeply, after which no macro is expanded anymore:
+    /// a macro using itself more than once would otherwise take forever to unwind.
+    too_deep: bool,
+    pub output: Vec<SourceLine>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Preprocessor {
+    pub fn new() -> Self {
+        Self {
+            defines: HashMap::new(),
+            macros: HashMap::new(),
+            includes: Vec::new(),
+            expansions: 0,
+            depth: 0,
+            too_deep: false,
+            output: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn error(&mut self, line: &SourceLine, span: Range<usize>, message: impl Into<String>) {
+        self.diagnostics
+            .push(Diagnostic::new(Severity::Error, line, span, message, None));
+    }
+
+    /// Preprocesses the lines of one file, `path` being where it was read from, if anywhere.
+    pub fn process_file(&mut self, path: Option<&Path>, lines: Vec<SourceLine>) {
+        if let Some(canonical) = path.and_then(|path| path.canonicalize().ok()) {
+            self.includes.push(canonical);
+            self.process(&lines);
+            self.includes.pop();
+        } else {
+            self.process(&lines);
+        }
+    }
+
+    fn process(&mut self, lines: &[SourceLine]) {
+        let mut conditions: Vec<Condition> = Vec::new();
+        let mut lines = lines.iter();
+
+        while let Some(line) = lines.next() {
+            let active = conditions.last().is_none_or(|condition| condition.active);
+            // lexical errors are left for the assembler to report
+            let Ok(tokens) = tokenize(&line.text) else {
+                if active {
+                    self.output.push(line.clone());
+                }
+                continue;
+            };
+
+            let directive = match tokens.first() {
+                Some(Token {
+                    kind: TokenKind::Directive(name),
+                    span,
+                }) => Some((name.as_str(), span.clone())),
+                _ => None,
+            };
+
+            match directive {
+                Some((name @ ("IFDEF" | "IFNDEF"), span)) => {
+                    let defined = match tokens.get(1) {
+                        Some(Token {
+                            kind: TokenKind::Word(word),
+                            ..
+                        }) => self.defines.contains_key(word),
+                        _ => {
+                            self.error(line, span.clone(), format!(".{name} needs a name"));
+                            false
+                        }
+                    };
+                    conditions.push(Condition {
+                        active: active && defined == (name == "IFDEF"),
+                        parent_active: active,
+                        seen_else: false,
+                        line: line.clone(),
+                        span,
+                    });
+                }
+                Some(("ELSE", span)) => match conditions.last_mut() {
+                    Some(condition) if condition.seen_else => {
+                        self.error(line, span, "second .ELSE for the same .IFDEF")
+                    }
+                    Some(condition) => {
+                        condition.active = condition.parent_active && !condition.active;
+                        condition.seen_else = true;
+                    }
+                    None => self.error(line, span, ".ELSE without .IFDEF or .IFNDEF"),
+                },
+                Some(("ENDIF", span)) => {
+                    if conditions.pop().is_none() {
+                        self.error(line, span, ".ENDIF without .IFDEF or .IFNDEF");
+                    }
+                }
+                _ if !active => {}
+                Some(("DEFINE", span)) => self.define(line, &tokens, span),
+                Some(("INCLUDE", span)) => self.include(line, &tokens, span),
+                Some(("MACRO", span)) => {
+                    let mut body = Vec::new();
+                    let mut closed = false;
+                    for body_line in lines.by_ref() {
+                        let first = tokenize(&body_line.text)
+                            .ok()
+                            .and_then(|tokens| tokens.into_iter().next());
+                        match first.map(|token| token.kind) {
+                            Some(TokenKind::Directive(name)) if name == "ENDM" => {
+                                closed = true;
+                                break;
+                            }
+                            _ => body.push(body_line.clone()),
+                        }
+                    }
+                    if !closed {
+                        self.error(line, span.clone(), ".MACRO without .ENDM");
+                    }
+                    self.define_macro(line, &tokens, span, body);
+                }
+                Some(("ENDM", span)) => self.error(line, span, ".ENDM without .MACRO"),
+                _ => self.expand(line, &tokens),
+            }
+        }
+
+        for condition in conditions {
+            self.error(
+                &condition.line,
+                condition.span,
+                "this conditional is never closed with .ENDIF",
+            );
+        }
+    }
+
+    /// `.DEFINE NAME value`; the value is optional, to define flags for `.IFDEF`.
+    fn define(&mut self, line: &SourceLine, tokens: &[Token], span: Range<usize>) {
+        let Some(Token {
+            kind: TokenKind::Word(name),
+            span: name_span,
+        }) = tokens.get(1)
+        else {
+            self.error(line, span, ".DEFINE needs a name");
+            return;
+        };
+
+        let value = match (tokens.get(2), tokens.last()) {
+            (Some(first), Some(last)) => {
+                let value = &line.text[first.span.start..last.span.end];
+                substitute(value, &self.defines)
+            }
+            _ => String::new(),
+        };
+        if self.defines.insert(name.clone(), value).is_some() {
+            self.diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                line,
+                name_span.clone(),
+                format!("`{name}` is defined again"),
+                None,
+            ));
+        }
+    }
+
+    /// `.INCLUDE "file.asm"`, relative to the directory of the including file.
+    fn include(&mut self, line: &SourceLine, tokens: &[Token], span: Range<usize>) {
+        let Some(Token {
+            kind: TokenKind::Str(name),
+            span: name_span,
+        }) = tokens.get(1)
+        else {
+            self.error(line, span, ".INCLUDE needs a file name in quotes");
+            return;
+        };
+
+        let directory = Path::new(&*line.file).parent().unwrap_or(Path::new(""));
+        let path = directory.join(name);
+        let canonical = path.canonicalize().ok();
+        if canonical.is_some() && self.includes.iter().any(|p| Some(p) == canonical.as_ref()) {
+            self.error(
+                line,
+                name_span.clone(),
+                format!("`{}` is already being included", path.display()),
+            );
+            return;
+        }
+
+        match fs::read_to_string(&path) {
+            Ok(source) => {
+                let lines = SourceLine::split(&path.display().to_string(), &source);
+                self.process_file(Some(&path), lines);
+            }
+            Err(err) => self.error(
+                line,
+                name_span.clone(),
+                format!("couldn't read `{}`: {err}", path.display()),
+            ),
+        }
+    }
+
+    /// `.MACRO NAME [PARAM {, PARAM}]`, with the body up to `.ENDM` already collected.
+    fn define_macro(
+        &mut self,
+        line: &SourceLine,
+        tokens: &[Token],
+        span: Range<usize>,
+        body: Vec<SourceLine>,
+    ) {
+        let Some(Token {
+            kind: TokenKind::Word(name),
+            span: name_span,
+        }) = tokens.get(1)
+        else {
+            self.error(line, span, ".MACRO needs a name");
+            return;
+        };
+        if Mnemonic::parse(name).is_some() {
+            self.error(
+                line,
+                name_span.clone(),
+                format!("`{name}` is an instruction and can't be a macro name"),
+            );
+            return;
+        }
+
+        let mut params = Vec::new();
+        for token in &tokens[2..] {
+            match &token.kind {
+                TokenKind::Word(param) => params.push(param.clone()),
+                TokenKind::Comma => {}
+                _ => {
+                    self.error(line, token.span.clone(), "expected a parameter name");
+                    return;
+                }
+            }
+        }
+
+        let labels = body
+            .iter()
+            .filter_map(|line| {
+                let tokens = tokenize(&line.text).ok()?;
+                match tokens.first()?.kind {
+                    TokenKind::Word(ref word)
+                        if Mnemonic::parse(word).is_none()
+                            && register(word).is_none()
+                            && !params.contains(word)
+                            && !word.eq_ignore_ascii_case(name)
+                            && !self.macros.contains_key(&word.to_ascii_uppercase()) =>
+                    {
+                        Some(word.clone())
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+
+        self.macros.insert(
+            name.to_ascii_uppercase(),
+            Macro {
+                params,
+                labels,
+                body,
+            },
+        );
+    }
+
+    /// Substitutes the constants in an ordinary line and expands it if it uses a macro.
+    fn expand(&mut self, line: &SourceLine, tokens: &[Token]) {
+        let macro_name = |token: Option<&Token>| match token {
+            Some(Token {
+                kind: TokenKind::Word(word),
+                span,
+            }) => {
+                let name = word.to_ascii_uppercase();
+                self.macros
+                    .contains_key(&name)
+                    .then(|| (name, span.clone()))
+            }
+            _ => None,
+        };
+        // a macro is used like an instruction, possibly after a label
+        let call = match tokens.first() {
+            Some(Token {
+                kind: TokenKind::Word(first),
+                ..
+            }) if Mnemonic::parse(first).is_none() => macro_name(tokens.first())
+                .map(|call| (0, call))
+                .or_else(|| macro_name(tokens.get(1)).map(|call| (1, call))),
+            _ => None,
+        };
+        let Some((index, (name, name_span))) = call else {
+            let mut line = line.clone();
+            line.text = substitute(&line.text, &self.defines);
+            self.output.push(line);
+            return;
+        };
+        if self.too_deep {
+            return;
+        }
+
+        let mut args = Vec::new();
+        for arg in tokens[index + 1..].split(|token| token.kind == TokenKind::Comma) {
+            match (arg.first(), arg.last()) {
+                (Some(first), Some(last)) => {
+                    let text = &line.text[first.span.start..last.span.end];
+                    args.push(substitute(text, &self.defines));
+                }
+                _ if tokens.len() > index + 1 => {
+                    self.error(line, name_span, "empty macro argument");
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        let arity = self.macros[&name].params.len();
+        if args.len() != arity {
+            let plural = if arity == 1 { "" } else { "s" };
+            let message = format!(
+                "macro `{name}` takes {arity} argument{plural}, found {}",
+                args.len()
+            );
+            self.error(line, name_span, message);
+            return;
+        }
+        if self.depth >= MAX_EXPANSION_DEPTH {
+            self.error(
+                line,
+                name_span,
+                format!("macro `{name}` is nested too deeply, does it use itself?"),
+            );
+            self.too_deep = true;
+            return;
+        }
+
+        // the label of the line, if any, labels the first line of the expansion
+        if index == 1 {
+            let mut label = line.clone();
+            label.text = line.text[tokens[0].span.clone()].to_string();
+            self.output.push(label);
+        }
+
+        self.expansions += 1;
+        let mac = &self.macros[&name];
+        let mut replacements: HashMap<String, String> =
+            mac.params.iter().cloned().zip(args).collect();
+        for label in &mac.labels {
+            replacements.insert(label.clone(), format!("{label}_{}", self.expansions));
+        }
+
+        let note: Rc<str> =
+            format!("in macro `{name}`, used at {}:{}", line.file, line.number).into();
+        let body: Vec<SourceLine> = mac
+            .body
+            .iter()
+            .map(|body_line| SourceLine {
+                text: substitute(&body_line.text, &replacements),
+                expansion: Some(note.clone()),
+                ..body_line.clone()
+            })
+            .collect();
+
+        self.depth += 1;
+        self.process(&body);
+        self.depth -= 1;
+    }
+}
+
+/// Replaces the words of `text` that are keys of `replacements`.
+fn substitute(text: &str, replacements: &HashMap<String, String>) -> String {
+    let Ok(tokens) = tokenize(text) else {
+        return text.to_string();
+    };
+
+    let mut result = String::new();
+    let mut copied = 0;
+    for token in tokens {
+        if let TokenKind::Word(word) = &token.kind {
+            if let Some(replacement) = replacements.get(word) {
+                result.push_str(&text[copied..token.span.start]);
+                result.push_str(replacement);
+                copied = token.span.end;
+            }
+        }
+    }
+    result.push_str(&text[copied..]);
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn preprocess(source: &str) -> Preprocessor {
+        let mut preprocessor = Preprocessor::new();
+        preprocessor.process_file(None, SourceLine::split("test.asm", source));
+
+        preprocessor
+    }
+
+    fn texts(preprocessor: &Preprocessor) -> Vec<&str> {
+        preprocessor
+            .output
+            .iter()
+            .map(|line| line.text.trim())
+            .collect()
+    }
+
+    #[test]
+    fn test_define() {
+        let preprocessor = preprocess(
+            ".DEFINE SIZE #10 ; words
+            .DEFINE TWICE SIZE, SIZE
+            ADD R0, R0, SIZE
+            .FILL SIZES",
+        );
+
+        assert_eq!(texts(&preprocessor), ["ADD R0, R0, #10", ".FILL SIZES"]);
+        assert!(preprocessor.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let preprocessor = preprocess(
+            ".DEFINE DEBUG
+            .IFDEF DEBUG
+            A
+            .IFNDEF DEBUG
+            B
+            .ELSE
+            C
+            .ENDIF
+            .ELSE
+            D
+            .ENDIF
+            .IFDEF RELEASE
+            E
+            .ENDIF",
+        );
+
+        assert_eq!(texts(&preprocessor), ["A", "C"]);
+        assert!(preprocessor.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_macros() {
+        let preprocessor = preprocess(
+            ".MACRO PUSH REG
+            ADD R6, R6, #-1
+            STR REG, R6, #0
+            .ENDM
+            .MACRO WAIT COUNT
+            LD R0, COUNT
+AGAIN       ADD R0, R0, #-1
+            BRp AGAIN
+            .ENDM
+START       push R7
+            WAIT DELAY
+            WAIT DELAY",
+        );
+
+        assert_eq!(
+            texts(&preprocessor),
+            [
+                "START",
+                "ADD R6, R6, #-1",
+                "STR R7, R6, #0",
+                "LD R0, DELAY",
+                "AGAIN_2       ADD R0, R0, #-1",
+                "BRp AGAIN_2",
+                "LD R0, DELAY",
+                "AGAIN_3       ADD R0, R0, #-1",
+                "BRp AGAIN_3",
+            ]
+        );
+
+        let line = &preprocessor.output[2];
+        assert_eq!(line.number, 3);
+        assert_eq!(
+            line.expansion.as_deref(),
+            Some("in macro `PUSH`, used at test.asm:10")
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let messages = |source: &str| -> Vec<String> {
+            preprocess(source)
+                .diagnostics
+                .into_iter()
+                .map(|diagnostic| diagnostic.message)
+                .collect()
+        };
+
+        assert_eq!(
+            messages(".IFDEF A\n.ENDIF\n.ENDIF\n.IFNDEF B"),
+            [
+                ".ENDIF without .IFDEF or .IFNDEF",
+                "this conditional is never closed with .ENDIF"
+            ]
+        );
+        assert_eq!(
+            messages(".MACRO TWO A, B\n.ENDM\nTWO R1"),
+            ["macro `TWO` takes 2 arguments, found 1"]
+        );
+        assert_eq!(
+            messages(".MACRO LOOP\nLOOP\n.ENDM\nLOOP"),
+            ["macro `LOOP` is nested too deeply, does it use itself?"]
+        );
+        // using itself twice would expand 2^64 times
+        assert_eq!(
+            messages(".MACRO A\nA\nA\n.ENDM\nA\nA"),
+            ["macro `A` is nested too deeply, does it use itself?"]
+        );
+        assert_eq!(
+            messages(".INCLUDE \"missing.asm\"")[0],
+            "couldn't read `missing.asm`: No such file or directory (os error 2)"
+        );
+    }
+}
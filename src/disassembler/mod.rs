@@ -0,0 +1,217 @@
+//! Turns words back into LC-3 assembly, e.g. to see what an `.obj` file contains.
+//!
+//! ```text
+//!         LEA R0, HELLO         ; x3000  xE002
+//! LOOP    ADD R1, R1, #-1       ; x3001  x127F
+//!         BRp LOOP              ; x3002  x03FE
+//! HELLO   .FILL x0048           ; x3003  x0048  'H'
+//! ```
+
+use crate::hardware::{
+    instruction::{trap_alias, Instruction, Operand},
+    symbol::SymbolTable,
+};
+
+/// One word as an instruction, e.g. `ADD R0, R1, #-3`, `BRnz LOOP` or `HALT`,
+/// or as `.FILL x1234` if it isn't an instruction the assembler would produce.
+///
+/// `address` is where the word is, for PC-relative operands: they're shown as the label
+/// `symbols` has at their target, or as the offset itself (`BRnz #-3`) if there's none.
+pub fn disassemble(word: u16, address: u16, symbols: &SymbolTable) -> String {
+    decode(word, address, symbols).unwrap_or_else(|| format!(".FILL x{word:04X}"))
+}
+
+/// A block of memory starting at `origin` as assembly source, one word per line, with its address
+/// and value in a comment.
+///
+/// Targets of branches, loads, ... inside the block that have no label in `symbols` get one named
+/// after their address, e.g. `L3005`, so the output reads (and assembles) like a program.
+pub fn disassemble_words(origin: u16, words: &[u16], symbols: &SymbolTable) -> String {
+    let mut symbols = symbols.clone();
+    let block = origin as usize..origin as usize + words.len();
+    for (offset, &word) in words.iter().enumerate() {
+        let address = origin.wrapping_add(offset as u16);
+        let Some(target) = pc_target(word, address) else {
+            continue;
+        };
+        if block.contains(&(target as usize)) && symbols.label_at(target).is_none() {
+            symbols.insert(&format!("L{target:04X}"), target);
+        }
+    }
+
+    let mut text = String::new();
+    for (offset, &word) in words.iter().enumerate() {
+        let address = origin.wrapping_add(offset as u16);
+        let label = symbols.label_at(address).unwrap_or_default();
+        let code = format!("{label:<7} {}", disassemble(word, address, &symbols));
+
+        text.push_str(&format!("{code:<29} ; x{address:04X}  x{word:04X}"));
+        if (0x20..0x7f).contains(&word) {
+            text.push_str(&format!("  '{}'", word as u8 as char));
+        }
+        text.push('\n');
+    }
+
+    text
+}
+
+fn decode(word: u16, address: u16, symbols: &SymbolTable) -> Option<String> {
+    let target = |offset: i16| {
+        let target = address.wrapping_add(1).wrapping_add(offset as u16);
+        match symbols.label_at(target) {
+            Some(label) => label.to_string(),
+            None => format!("#{offset}"),
+        }
+    };
+    let operand = |operand| match operand {
+        Operand::Register(sr2) => format!("R{sr2}"),
+        Operand::Immediate(imm5) => format!("#{imm5}"),
+    };
+
+    let text = match Instruction::decode(word) {
+        // never branches, so it's more likely data
+        Instruction::Br {
+            n: false,
+            z: false,
+            p: false,
+            ..
+        } => return None,
+        Instruction::Br { n, z, p, offset } => {
+            let conditions: String = [(n, 'n'), (z, 'z'), (p, 'p')]
+                .iter()
+                .filter(|(set, _)| *set)
+                .map(|(_, flag)| flag)
+                .collect();
+            match conditions.as_str() {
+                "nzp" => format!("BR {}", target(offset)),
+                conditions => format!("BR{conditions} {}", target(offset)),
+            }
+        }
+        Instruction::Add {
+            dr,
+            sr1,
+            operand: second,
+            unused: 0,
+        } => format!("ADD R{dr}, R{sr1}, {}", operand(second)),
+        Instruction::And {
+            dr,
+            sr1,
+            operand: second,
+            unused: 0,
+        } => format!("AND R{dr}, R{sr1}, {}", operand(second)),
+        Instruction::Ld { dr, offset } => format!("LD R{dr}, {}", target(offset)),
+        Instruction::Ldi { dr, offset } => format!("LDI R{dr}, {}", target(offset)),
+        Instruction::Lea { dr, offset } => format!("LEA R{dr}, {}", target(offset)),
+        Instruction::St { sr, offset } => format!("ST R{sr}, {}", target(offset)),
+        Instruction::Sti { sr, offset } => format!("STI R{sr}, {}", target(offset)),
+        Instruction::Ldr { dr, base, offset } => format!("LDR R{dr}, R{base}, #{offset}"),
+        Instruction::Str { sr, base, offset } => format!("STR R{sr}, R{base}, #{offset}"),
+        Instruction::Jsr { offset } => format!("JSR {}", target(offset)),
+        Instruction::Jsrr { base, unused: 0 } => format!("JSRR R{base}"),
+        Instruction::Not { dr, sr, unused: 0 } => format!("NOT R{dr}, R{sr}"),
+        Instruction::Jmp { base: 7, unused: 0 } => "RET".to_string(),
+        Instruction::Jmp { base, unused: 0 } => format!("JMP R{base}"),
+        Instruction::Rti { unused: 0 } => "RTI".to_string(),
+        Instruction::Trap { vector, unused: 0 } => match trap_alias(vector) {
+            Some(alias) => alias.to_string(),
+            None => format!("TRAP x{vector:02X}"),
+        },
+        // bits the hardware ignores aren't what the assembler writes, or the reserved opcode
+        Instruction::Add { .. }
+        | Instruction::And { .. }
+        | Instruction::Jsrr { .. }
+        | Instruction::Not { .. }
+        | Instruction::Jmp { .. }
+        | Instruction::Rti { .. }
+        | Instruction::Trap { .. }
+        | Instruction::Reserved { .. } => return None,
+    };
+
+    Some(text)
+}
+
+/// Where a PC-relative instruction points to.
+fn pc_target(word: u16, address: u16) -> Option<u16> {
+    let offset = match Instruction::decode(word) {
+        Instruction::Br {
+            n: false,
+            z: false,
+            p: false,
+            ..
+        } => return None,
+        instruction => instruction.pc_offset()?,
+    };
+
+    Some(address.wrapping_add(1).wrapping_add(offset as u16))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assemble;
+
+    #[test]
+    fn test_disassemble() {
+        let mut symbols = SymbolTable::new();
+        symbols.insert("LOOP", 0x2ffe);
+        let text = |word| disassemble(word, 0x3000, &symbols);
+
+        assert_eq!(text(0x103d), "ADD R0, R0, #-3");
+        assert_eq!(text(0x5042), "AND R0, R1, R2");
+        assert_eq!(text(0x0dfd), "BRnz LOOP");
+        assert_eq!(text(0x0e05), "BR #5");
+        assert_eq!(text(0xf025), "HALT");
+        assert_eq!(text(0xf026), "TRAP x26");
+        assert_eq!(text(0xc1c0), "RET");
+        assert_eq!(text(0x4080), "JSRR R2");
+        assert_eq!(text(0x6ba0), "LDR R5, R6, #-32");
+        assert_eq!(text(0x973f), "NOT R3, R4");
+        assert_eq!(text(0x0048), ".FILL x0048");
+        assert_eq!(text(0xd000), ".FILL xD000");
+        // SR2 mode with bits 4:3 set isn't something the assembler writes
+        assert_eq!(text(0x1018), ".FILL x1018");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let source = "        .ORIG x3000
+        LEA R0, HELLO
+LOOP    ADD R1, R1, #-1
+        BRp LOOP
+        JSR SUB
+        LDI R2, PTR
+        PUTS
+        HALT
+SUB     RET
+PTR     .FILL HELLO
+HELLO   .STRINGZ \"Hi\"
+        .END";
+        let assembly = assemble(source).unwrap();
+        let image = &assembly.image;
+
+        let with_labels = disassemble_words(image.origin, &image.words, &assembly.symbols);
+        assert!(with_labels.starts_with(
+            "        LEA R0, HELLO         ; x3000  xE008
+LOOP    ADD R1, R1, #-1       ; x3001  x127F
+        BRp LOOP              ; x3002  x03FE
+"
+        ));
+        assert!(with_labels.ends_with(
+            "HELLO   .FILL x0048           ; x3009  x0048  'H'
+        .FILL x0069           ; x300A  x0069  'i'
+        .FILL x0000           ; x300B  x0000
+"
+        ));
+
+        // without a symbol table, labels are made up for the targets
+        let without_labels = disassemble_words(image.origin, &image.words, &SymbolTable::new());
+        assert!(without_labels.contains("L3001   ADD R1, R1, #-1"));
+        assert!(without_labels.contains("        JSR L3007"));
+
+        // and either way, it assembles back to the same image
+        for body in [with_labels, without_labels] {
+            let source = format!(".ORIG x3000\n{body}.END");
+            assert_eq!(&assemble(&source).unwrap().image, image);
+        }
+    }
+}
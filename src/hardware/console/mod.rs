@@ -0,0 +1,204 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    rc::Rc,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+/// Where the VM reads keystrokes from and writes characters to.
+/// Traps and the keyboard device go through this instead of the process-global streams,
+/// so the machine can run headless, against files, or inside another program's UI.
+pub trait Console {
+    /// Waits for the next input byte.
+    fn read_byte(&mut self) -> io::Result<u8>;
+
+    /// Returns the next input byte if one is available.
+    fn poll_byte(&mut self) -> io::Result<Option<u8>>;
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()>;
+
+    fn flush(&mut self) -> io::Result<()>;
+}
+
+/// The console is shared between the VM (traps) and the memory-mapped devices.
+pub type SharedConsole = Rc<RefCell<dyn Console>>;
+
+/// The process' stdin and stdout, i.e. the real terminal.
+/// Stdin is read by a background thread into a queue, so polling never blocks.
+/// The thread is only started on the first read.
+#[derive(Debug, Default)]
+pub struct TerminalConsole {
+    input: Option<Receiver<u8>>,
+}
+
+impl TerminalConsole {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn input(&mut self) -> &Receiver<u8> {
+        self.input.get_or_insert_with(spawn_stdin_reader)
+    }
+}
+
+fn spawn_stdin_reader() -> Receiver<u8> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        for byte in io::stdin().lock().bytes() {
+            // stop on a read error or once the console has been dropped
+            let Ok(byte) = byte else { break };
+            if sender.send(byte).is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+fn stdin_closed() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "stdin closed")
+}
+
+impl Console for TerminalConsole {
+    fn read_byte(&mut self) -> io::Result<u8> {
+        self.input().recv().map_err(|_| stdin_closed())
+    }
+
+    fn poll_byte(&mut self) -> io::Result<Option<u8>> {
+        match self.input().try_recv() {
+            Ok(byte) => Ok(Some(byte)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(stdin_closed()),
+        }
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        io::stdout().write_all(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+/// An in-memory console: input is queued up front, output is collected for inspection.
+#[derive(Debug, Default)]
+pub struct BufferConsole {
+    input: VecDeque<u8>,
+    output: Vec<u8>,
+}
+
+impl BufferConsole {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_input(input: &[u8]) -> Self {
+        Self {
+            input: input.iter().copied().collect(),
+            output: Vec::new(),
+        }
+    }
+
+    pub fn push_input(&mut self, input: &[u8]) {
+        self.input.extend(input);
+    }
+
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+}
+
+impl Console for BufferConsole {
+    fn read_byte(&mut self) -> io::Result<u8> {
+        self.input
+            .pop_front()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "console input exhausted"))
+    }
+
+    fn poll_byte(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.input.pop_front())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.output.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads input from one file and writes output to another.
+#[derive(Debug)]
+pub struct FileConsole {
+    input: BufReader<File>,
+    output: BufWriter<File>,
+}
+
+impl FileConsole {
+    /// Opens `input` for reading and creates (or truncates) `output` for writing.
+    pub fn open<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q) -> io::Result<Self> {
+        Ok(Self {
+            input: BufReader::new(File::open(input)?),
+            output: BufWriter::new(File::create(output)?),
+        })
+    }
+}
+
+impl Console for FileConsole {
+    fn read_byte(&mut self) -> io::Result<u8> {
+        let mut buf = [0; 1];
+        self.input.read_exact(&mut buf)?;
+
+        Ok(buf[0])
+    }
+
+    // the end of the input file means no key will ever be ready
+    fn poll_byte(&mut self) -> io::Result<Option<u8>> {
+        match self.read_byte() {
+            Ok(byte) => Ok(Some(byte)),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.output.write_all(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_buffer_console() {
+        let mut console = BufferConsole::with_input(b"ab");
+
+        assert_eq!(console.read_byte().unwrap(), b'a');
+        assert_eq!(console.poll_byte().unwrap(), Some(b'b'));
+        assert_eq!(console.poll_byte().unwrap(), None);
+        assert!(console.read_byte().is_err());
+
+        console.write_bytes(b"hi").unwrap();
+
+        assert_eq!(console.take_output(), b"hi");
+        assert!(console.output().is_empty());
+    }
+}
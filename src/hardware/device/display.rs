@@ -0,0 +1,81 @@
+use super::Device;
+use crate::hardware::console::SharedConsole;
+
+pub const DSR: u16 = 0xfe04; // display status
+pub const DDR: u16 = 0xfe06; // display data
+
+const READY: u16 = 1 << 15;
+
+/// The display as LC-3 programs see it through DSR and DDR.
+/// Characters written to DDR go straight to the console, so DSR always reports ready.
+pub struct Display {
+    console: SharedConsole,
+    data: u16,
+}
+
+impl Display {
+    pub fn new(console: SharedConsole) -> Self {
+        Self { console, data: 0 }
+    }
+
+    pub fn status(&self) -> u16 {
+        READY
+    }
+
+    pub fn data(&self) -> u16 {
+        self.data
+    }
+
+    /// Writing DDR prints the low byte of `value`.
+    pub fn write_data(&mut self, value: u16) {
+        self.data = value;
+
+        // the program has no way to observe a failed write, so it is dropped like on a real display
+        let mut console = self.console.borrow_mut();
+        let _ = console.write_bytes(&[value as u8]);
+        let _ = console.flush();
+    }
+}
+
+impl Device for Display {
+    fn read(&mut self, addr: u16) -> u16 {
+        self.peek(addr)
+    }
+
+    fn peek(&self, addr: u16) -> u16 {
+        match addr {
+            DSR => self.status(),
+            _ => self.data(),
+        }
+    }
+
+    // DSR is read-only
+    fn write(&mut self, addr: u16, value: u16) {
+        if addr == DDR {
+            self.write_data(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::hardware::console::BufferConsole;
+
+    use super::*;
+
+    #[test]
+    fn test_write_data() {
+        let console = Rc::new(RefCell::new(BufferConsole::new()));
+        let mut display = Display::new(console.clone());
+
+        assert_eq!(display.status(), READY);
+
+        display.write_data(b'h' as u16);
+        display.write_data(b'i' as u16);
+
+        assert_eq!(console.borrow().output(), b"hi");
+        assert_eq!(display.data(), b'i' as u16);
+    }
+}
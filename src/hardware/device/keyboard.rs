@@ -0,0 +1,162 @@
+use super::Device;
+use crate::hardware::{console::SharedConsole, interrupt::Interrupt};
+
+pub const KBSR: u16 = 0xfe00; // keyboard status
+pub const KBDR: u16 = 0xfe02; // keyboard data
+
+const READY: u16 = 1 << 15;
+const INTERRUPT_ENABLE: u16 = 1 << 14;
+
+pub const INTERRUPT: Interrupt = Interrupt {
+    vector: 0x80,
+    priority: 4,
+};
+
+/// The keyboard as LC-3 programs see it through KBSR and KBDR.
+/// Bytes are pulled from the console without blocking; once a byte is latched KBSR
+/// reports ready until the program reads it from KBDR, like the real hardware.
+/// Setting bit 14 of KBSR makes the keyboard request an interrupt whenever a key is ready.
+pub struct Keyboard {
+    console: SharedConsole,
+    data: u16,
+    ready: bool,
+    interrupt_enabled: bool,
+}
+
+impl Keyboard {
+    pub fn new(console: SharedConsole) -> Self {
+        Self {
+            console,
+            data: 0,
+            ready: false,
+            interrupt_enabled: false,
+        }
+    }
+
+    /// Latches the next input byte if none is waiting to be read.
+    pub fn poll(&mut self) {
+        if self.ready {
+            return;
+        }
+
+        // a failed read is reported to the program as "no key ready"
+        if let Ok(Some(key)) = self.console.borrow_mut().poll_byte() {
+            self.data = key as u16;
+            self.ready = true;
+        }
+    }
+
+    pub fn status(&self) -> u16 {
+        let ready = if self.ready { READY } else { 0 };
+        let interrupt_enabled = if self.interrupt_enabled {
+            INTERRUPT_ENABLE
+        } else {
+            0
+        };
+
+        ready | interrupt_enabled
+    }
+
+    pub fn data(&self) -> u16 {
+        self.data
+    }
+
+    /// Reading KBDR hands the byte to the program and clears the ready bit.
+    pub fn take_data(&mut self) -> u16 {
+        self.ready = false;
+        self.data
+    }
+}
+
+impl Device for Keyboard {
+    fn read(&mut self, addr: u16) -> u16 {
+        match addr {
+            KBSR => {
+                self.poll();
+                self.status()
+            }
+            _ => self.take_data(),
+        }
+    }
+
+    fn peek(&self, addr: u16) -> u16 {
+        match addr {
+            KBSR => self.status(),
+            _ => self.data(),
+        }
+    }
+
+    // only the interrupt enable bit of KBSR is writable
+    fn write(&mut self, addr: u16, value: u16) {
+        if addr == KBSR {
+            self.interrupt_enabled = value & INTERRUPT_ENABLE != 0;
+        }
+    }
+
+    // keys are only fetched in the background when they can raise an interrupt,
+    // otherwise they stay in the console for GETC and IN
+    fn tick(&mut self) {
+        if self.interrupt_enabled {
+            self.poll();
+        }
+    }
+
+    fn interrupt(&self) -> Option<Interrupt> {
+        (self.interrupt_enabled && self.ready).then_some(INTERRUPT)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::hardware::console::BufferConsole;
+
+    use super::*;
+
+    #[test]
+    fn test_no_key_ready() {
+        let mut keyboard = Keyboard::new(Rc::new(RefCell::new(BufferConsole::new())));
+
+        keyboard.poll();
+
+        assert_eq!(keyboard.status(), 0);
+    }
+
+    #[test]
+    fn test_key_latched_until_read() {
+        let console = Rc::new(RefCell::new(BufferConsole::with_input(b"ab")));
+        let mut keyboard = Keyboard::new(console);
+
+        keyboard.poll();
+        keyboard.poll();
+
+        assert_eq!(keyboard.status(), READY);
+        assert_eq!(keyboard.take_data(), b'a' as u16);
+        assert_eq!(keyboard.status(), 0);
+
+        keyboard.poll();
+
+        assert_eq!(keyboard.take_data(), b'b' as u16);
+    }
+
+    #[test]
+    fn test_interrupt() {
+        let console = Rc::new(RefCell::new(BufferConsole::with_input(b"a")));
+        let mut keyboard = Keyboard::new(console);
+
+        keyboard.tick();
+
+        assert_eq!(keyboard.interrupt(), None);
+
+        keyboard.write(KBSR, INTERRUPT_ENABLE);
+        keyboard.tick();
+
+        assert_eq!(keyboard.status(), READY | INTERRUPT_ENABLE);
+        assert_eq!(keyboard.interrupt(), Some(INTERRUPT));
+
+        keyboard.read(KBDR);
+
+        assert_eq!(keyboard.interrupt(), None);
+    }
+}
@@ -0,0 +1,39 @@
+use super::Device;
+
+pub const MCR: u16 = 0xfffe; // machine control
+
+pub const CLOCK_ENABLE: u16 = 1 << 15;
+
+/// The Machine Control Register. The machine runs as long as bit 15 is set;
+/// an LC-3 operating system halts it by clearing that bit.
+pub struct MachineControl {
+    value: u16,
+}
+
+impl MachineControl {
+    pub fn new() -> Self {
+        Self {
+            value: CLOCK_ENABLE,
+        }
+    }
+}
+
+impl Device for MachineControl {
+    fn read(&mut self, addr: u16) -> u16 {
+        self.peek(addr)
+    }
+
+    fn peek(&self, _addr: u16) -> u16 {
+        self.value
+    }
+
+    fn write(&mut self, _addr: u16, value: u16) {
+        self.value = value;
+    }
+}
+
+impl Default for MachineControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,227 @@
+use std::{error::Error, fmt};
+
+use super::interrupt::Interrupt;
+
+pub mod display;
+pub mod keyboard;
+pub mod mcr;
+pub mod timer;
+
+pub const IO_PAGE_START: u16 = 0xfe00;
+const IO_PAGE_SIZE: usize = 0x200; // xFE00 - xFFFF
+
+/// A peripheral mapped into the I/O page.
+/// Reads and writes to any address the device is attached at are routed to it instead of RAM.
+pub trait Device {
+    /// Called when the program reads `addr`; may have side effects (e.g. consuming a key).
+    fn read(&mut self, addr: u16) -> u16;
+
+    /// Returns what a read of `addr` would, without side effects.
+    fn peek(&self, addr: u16) -> u16;
+
+    fn write(&mut self, addr: u16, value: u16);
+
+    /// Called once per instruction, before it is fetched.
+    fn tick(&mut self) {}
+
+    /// The interrupt this device is currently requesting, if any.
+    /// The request stays raised until the device clears it (e.g. when its data is read).
+    fn interrupt(&self) -> Option<Interrupt> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusError {
+    /// Devices can only be attached in the I/O page (xFE00 - xFFFF).
+    OutsideIoPage { addr: u16 },
+}
+
+impl fmt::Display for BusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BusError::OutsideIoPage { addr } => {
+                write!(f, "x{addr:04X} is outside of the I/O page")
+            }
+        }
+    }
+}
+
+impl Error for BusError {}
+
+/// Routes accesses in the I/O page to the attached devices.
+pub struct DeviceBus {
+    devices: Vec<Box<dyn Device>>,
+    slots: [Option<usize>; IO_PAGE_SIZE],
+}
+
+impl DeviceBus {
+    pub fn new() -> Self {
+        Self {
+            devices: Vec::new(),
+            slots: [None; IO_PAGE_SIZE],
+        }
+    }
+
+    /// Attaches `device` at every address in `addrs`, replacing whatever was attached there.
+    /// A device replaced at all of its addresses is removed: it isn't ticked anymore and can't
+    /// raise interrupts.
+    pub fn attach<I>(&mut self, addrs: I, device: Box<dyn Device>) -> Result<(), BusError>
+    where
+        I: IntoIterator<Item = u16>,
+    {
+        let addrs: Vec<u16> = addrs.into_iter().collect();
+
+        if let Some(&addr) = addrs.iter().find(|&&addr| addr < IO_PAGE_START) {
+            return Err(BusError::OutsideIoPage { addr });
+        }
+
+        self.devices.push(device);
+        for addr in addrs {
+            self.slots[(addr - IO_PAGE_START) as usize] = Some(self.devices.len() - 1);
+        }
+        self.remove_unreachable();
+
+        Ok(())
+    }
+
+    /// Drops the devices no address leads to anymore, and renumbers the slots of the others.
+    fn remove_unreachable(&mut self) {
+        let mut renumbered = vec![None; self.devices.len()];
+        let mut devices = Vec::new();
+        for (index, device) in std::mem::take(&mut self.devices).into_iter().enumerate() {
+            if self.slots.contains(&Some(index)) {
+                renumbered[index] = Some(devices.len());
+                devices.push(device);
+            }
+        }
+
+        self.devices = devices;
+        for slot in self.slots.iter_mut() {
+            *slot = slot.and_then(|index| renumbered[index]);
+        }
+    }
+
+    fn slot(&self, addr: u16) -> Option<usize> {
+        if addr < IO_PAGE_START {
+            return None;
+        }
+
+        self.slots[(addr - IO_PAGE_START) as usize]
+    }
+
+    pub fn device(&self, addr: u16) -> Option<&dyn Device> {
+        self.slot(addr).map(|index| self.devices[index].as_ref())
+    }
+
+    pub fn device_mut(&mut self, addr: u16) -> Option<&mut (dyn Device + 'static)> {
+        self.slot(addr).map(|index| self.devices[index].as_mut())
+    }
+
+    pub fn tick(&mut self) {
+        for device in self.devices.iter_mut() {
+            device.tick();
+        }
+    }
+
+    /// The highest priority interrupt requested by any device.
+    pub fn pending_interrupt(&self) -> Option<Interrupt> {
+        self.devices
+            .iter()
+            .filter_map(|device| device.interrupt())
+            .max_by_key(|interrupt| interrupt.priority)
+    }
+}
+
+impl Default for DeviceBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A port that hands out a new number on every read.
+    struct Counter(u16);
+
+    impl Device for Counter {
+        fn read(&mut self, _addr: u16) -> u16 {
+            self.0 += 1;
+            self.0
+        }
+
+        fn peek(&self, _addr: u16) -> u16 {
+            self.0
+        }
+
+        fn write(&mut self, _addr: u16, value: u16) {
+            self.0 = value;
+        }
+    }
+
+    #[test]
+    fn test_attach() {
+        let mut bus = DeviceBus::new();
+
+        bus.attach([0xfe10], Box::new(Counter(0))).unwrap();
+
+        let device = bus.device_mut(0xfe10).unwrap();
+        device.write(0xfe10, 41);
+
+        assert_eq!(device.read(0xfe10), 42);
+        assert!(bus.device(0xfe12).is_none());
+    }
+
+    /// A device that always asks for an interrupt.
+    struct Alarm;
+
+    impl Device for Alarm {
+        fn read(&mut self, _addr: u16) -> u16 {
+            0
+        }
+
+        fn peek(&self, _addr: u16) -> u16 {
+            0
+        }
+
+        fn write(&mut self, _addr: u16, _value: u16) {}
+
+        fn interrupt(&self) -> Option<Interrupt> {
+            Some(Interrupt {
+                vector: 0x81,
+                priority: 4,
+            })
+        }
+    }
+
+    #[test]
+    fn test_attach_over_device() {
+        let mut bus = DeviceBus::new();
+
+        bus.attach([0xfe10, 0xfe12], Box::new(Alarm)).unwrap();
+        bus.attach([0xfe14], Box::new(Counter(7))).unwrap();
+        bus.attach([0xfe10], Box::new(Counter(1))).unwrap();
+
+        // still reachable at xFE12
+        assert!(bus.pending_interrupt().is_some());
+
+        bus.attach([0xfe12], Box::new(Counter(2))).unwrap();
+
+        assert!(bus.pending_interrupt().is_none());
+        assert_eq!(bus.device(0xfe10).unwrap().peek(0xfe10), 1);
+        assert_eq!(bus.device(0xfe12).unwrap().peek(0xfe12), 2);
+        assert_eq!(bus.device(0xfe14).unwrap().peek(0xfe14), 7);
+    }
+
+    #[test]
+    fn test_attach_outside_io_page() {
+        let mut bus = DeviceBus::new();
+
+        let result = bus.attach([0xfe10, 0x3000], Box::new(Counter(0)));
+
+        assert_eq!(result, Err(BusError::OutsideIoPage { addr: 0x3000 }));
+        assert!(bus.device(0xfe10).is_none());
+    }
+}
@@ -0,0 +1,115 @@
+use super::Device;
+use crate::hardware::interrupt::Interrupt;
+
+pub const TMR: u16 = 0xfe08; // timer status
+pub const TMI: u16 = 0xfe0a; // timer interval, in instructions
+
+const FIRED: u16 = 1 << 15;
+const INTERRUPT_ENABLE: u16 = 1 << 14;
+
+pub const INTERRUPT: Interrupt = Interrupt {
+    vector: 0x81,
+    priority: 4,
+};
+
+/// A timer that fires every TMI instructions; an interval of 0 stops it.
+/// Bit 15 of TMR is set when the timer fires and cleared when TMR is read.
+/// Setting bit 14 of TMR makes the timer request an interrupt when it fires.
+#[derive(Debug, Default)]
+pub struct Timer {
+    interval: u16,
+    elapsed: u16,
+    fired: bool,
+    interrupt_enabled: bool,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Device for Timer {
+    fn read(&mut self, addr: u16) -> u16 {
+        let value = self.peek(addr);
+        if addr == TMR {
+            self.fired = false;
+        }
+
+        value
+    }
+
+    fn peek(&self, addr: u16) -> u16 {
+        match addr {
+            TMR => {
+                let fired = if self.fired { FIRED } else { 0 };
+                let interrupt_enabled = if self.interrupt_enabled {
+                    INTERRUPT_ENABLE
+                } else {
+                    0
+                };
+
+                fired | interrupt_enabled
+            }
+            _ => self.interval,
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u16) {
+        match addr {
+            TMR => self.interrupt_enabled = value & INTERRUPT_ENABLE != 0,
+            _ => {
+                self.interval = value;
+                self.elapsed = 0;
+            }
+        }
+    }
+
+    fn tick(&mut self) {
+        if self.interval == 0 {
+            return;
+        }
+
+        self.elapsed += 1;
+        if self.elapsed >= self.interval {
+            self.elapsed = 0;
+            self.fired = true;
+        }
+    }
+
+    fn interrupt(&self) -> Option<Interrupt> {
+        (self.interrupt_enabled && self.fired).then_some(INTERRUPT)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fires_every_interval() {
+        let mut timer = Timer::new();
+
+        timer.write(TMI, 3);
+        timer.write(TMR, INTERRUPT_ENABLE);
+        timer.tick();
+        timer.tick();
+
+        assert_eq!(timer.interrupt(), None);
+
+        timer.tick();
+
+        assert_eq!(timer.interrupt(), Some(INTERRUPT));
+        assert_eq!(timer.read(TMR), FIRED | INTERRUPT_ENABLE);
+        assert_eq!(timer.interrupt(), None);
+    }
+
+    #[test]
+    fn test_stopped() {
+        let mut timer = Timer::new();
+
+        timer.tick();
+
+        assert_eq!(timer.peek(TMR), 0);
+    }
+}
@@ -0,0 +1,100 @@
+use std::{error::Error, fmt, io};
+
+use super::symbol::SymbolTable;
+
+/// Every way the VM can stop executing other than a clean halt.
+/// Returned from the run loop so embedders decide what to do instead of the process exiting.
+#[derive(Debug)]
+pub enum VmError {
+    /// The word at `pc` doesn't encode an instruction the VM can execute (e.g. the reserved opcode).
+    IllegalOpcode { pc: u16, instr: u16 },
+    /// A privileged instruction (RTI) ran while the machine is in user mode.
+    PrivilegeModeViolation { pc: u16, instr: u16 },
+    /// User-mode code accessed system space (x0000 - x2FFF) or the I/O page.
+    AccessControlViolation { pc: u16, addr: u16 },
+    /// TRAP was issued with a vector that has no service routine.
+    UnknownTrap { vector: u8 },
+    /// A register index outside of R0-R7, PC and COND.
+    InvalidRegister { index: u16 },
+    /// Reading from or writing to the console failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, &SymbolTable::new())
+    }
+}
+
+/// A `VmError` that names addresses after the closest label, see [`VmError::with_symbols`].
+pub struct WithSymbols<'a> {
+    error: &'a VmError,
+    symbols: &'a SymbolTable,
+}
+
+impl fmt::Display for WithSymbols<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.write(f, self.symbols)
+    }
+}
+
+impl VmError {
+    /// Displays the error with addresses such as `LOOP+3` instead of `x3012`.
+    pub fn with_symbols<'a>(&'a self, symbols: &'a SymbolTable) -> WithSymbols<'a> {
+        WithSymbols {
+            error: self,
+            symbols,
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, symbols: &SymbolTable) -> fmt::Result {
+        match self {
+            VmError::IllegalOpcode { pc, instr } => {
+                write!(
+                    f,
+                    "illegal opcode x{instr:04X} at {}",
+                    symbols.describe(*pc)
+                )
+            }
+            VmError::PrivilegeModeViolation { pc, instr } => write!(
+                f,
+                "privilege mode violation: x{instr:04X} at {}",
+                symbols.describe(*pc)
+            ),
+            VmError::AccessControlViolation { pc, addr } => write!(
+                f,
+                "access control violation: {} accessed at {}",
+                symbols.describe(*addr),
+                symbols.describe(*pc)
+            ),
+            VmError::UnknownTrap { vector } => write!(f, "unknown trap vector x{vector:02X}"),
+            VmError::InvalidRegister { index } => write!(f, "invalid register index {index}"),
+            VmError::Io(err) => write!(f, "I/O error: {err}"),
+        }
+    }
+
+    /// The exception vector LC-3 code can handle this error with, if any.
+    pub fn exception_vector(&self) -> Option<u8> {
+        match self {
+            VmError::PrivilegeModeViolation { .. } => Some(0x00),
+            VmError::IllegalOpcode { .. } => Some(0x01),
+            VmError::AccessControlViolation { .. } => Some(0x02),
+            _ => None,
+        }
+    }
+}
+
+impl Error for VmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            VmError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for VmError {
+    fn from(err: io::Error) -> Self {
+        VmError::Io(err)
+    }
+}
@@ -0,0 +1,358 @@
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use byteorder::{BigEndian, ByteOrder};
+
+const ADDRESS_SPACE: usize = 1 << 16;
+
+/// The file formats an image can be stored in. In all of them the first word is the origin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// `.obj`: big-endian 16-bit words, as produced by `lc3as`.
+    Object,
+    /// `.hex`: one 4-digit hex word per line.
+    Hex,
+    /// `.bin`: one 16-digit binary word per line.
+    Binary,
+}
+
+impl ImageFormat {
+    pub fn from_extension<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            "obj" => Some(ImageFormat::Object),
+            "hex" => Some(ImageFormat::Hex),
+            "bin" => Some(ImageFormat::Binary),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the content: text where every line is a word is hex or binary,
+    /// anything else is an object file.
+    pub fn detect(bytes: &[u8]) -> Self {
+        let Ok(text) = std::str::from_utf8(bytes) else {
+            return ImageFormat::Object;
+        };
+
+        let mut lines = text_lines(text).map(|(_, line)| line).peekable();
+        if lines.peek().is_none() {
+            return ImageFormat::Object;
+        }
+
+        let is_word = |line: &str, digits: usize, radix: u32| {
+            line.len() == digits && line.chars().all(|c| c.is_digit(radix))
+        };
+        let (mut hex, mut binary) = (true, true);
+        for line in lines {
+            hex &= is_word(line, 4, 16);
+            binary &= is_word(line, 16, 2);
+        }
+
+        match (binary, hex) {
+            (true, _) => ImageFormat::Binary,
+            (false, true) => ImageFormat::Hex,
+            (false, false) => ImageFormat::Object,
+        }
+    }
+}
+
+/// The non-blank lines of a text image with their 1-based line numbers.
+fn text_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+}
+
+/// An LC-3 object image: an origin followed by the words to load there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub origin: u16,
+    pub words: Vec<u16>,
+}
+
+impl Image {
+    fn new(origin: u16, words: Vec<u16>) -> Result<Self, LoadError> {
+        if origin as usize + words.len() > ADDRESS_SPACE {
+            return Err(LoadError::AddressOverflow {
+                origin,
+                len: words.len(),
+            });
+        }
+
+        Ok(Self { origin, words })
+    }
+
+    /// Reads an image in any of the supported formats, see [`ImageFormat::detect`].
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, LoadError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        Self::parse(&bytes, ImageFormat::detect(&bytes))
+    }
+
+    pub fn parse(bytes: &[u8], format: ImageFormat) -> Result<Self, LoadError> {
+        match format {
+            ImageFormat::Object => Self::from_bytes(bytes),
+            ImageFormat::Hex => Self::from_text(bytes, 4, 16),
+            ImageFormat::Binary => Self::from_text(bytes, 16, 2),
+        }
+    }
+
+    fn from_text(bytes: &[u8], digits: usize, radix: u32) -> Result<Self, LoadError> {
+        let text = std::str::from_utf8(bytes).map_err(|_| LoadError::NotText)?;
+
+        let mut words = Vec::new();
+        for (line_number, line) in text_lines(text) {
+            let word = match line.len() == digits {
+                true => u16::from_str_radix(line, radix).ok(),
+                false => None,
+            };
+            let word = word.ok_or_else(|| LoadError::InvalidWord {
+                line: line_number,
+                text: line.to_string(),
+            })?;
+
+            words.push(word);
+        }
+
+        if words.is_empty() {
+            return Err(LoadError::Empty);
+        }
+        let origin = words.remove(0);
+
+        Self::new(origin, words)
+    }
+
+    /// Parses an image in the `.obj` format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, LoadError> {
+        if bytes.is_empty() {
+            return Err(LoadError::Empty);
+        }
+        if !bytes.len().is_multiple_of(2) {
+            return Err(LoadError::OddByteCount { len: bytes.len() });
+        }
+
+        let origin = BigEndian::read_u16(bytes);
+        let words: Vec<u16> = bytes[2..]
+            .chunks_exact(2)
+            .map(BigEndian::read_u16)
+            .collect();
+
+        Self::new(origin, words)
+    }
+
+    /// The image in the `.obj` format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0; 2 * (self.words.len() + 1)];
+        BigEndian::write_u16(&mut bytes, self.origin);
+        BigEndian::write_u16_into(&self.words, &mut bytes[2..]);
+
+        bytes
+    }
+}
+
+/// Where an image ended up in memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedImage {
+    pub origin: u16,
+    /// Number of words loaded, starting at `origin`.
+    pub len: usize,
+    /// Earlier images this one partially overwrote, only possible with `OverlapPolicy::Warn`.
+    pub overlaps: Vec<LoadedImage>,
+}
+
+impl LoadedImage {
+    pub fn new(origin: u16, len: usize) -> Self {
+        Self {
+            origin,
+            len,
+            overlaps: Vec::new(),
+        }
+    }
+
+    /// One past the last address of the image.
+    pub fn end(&self) -> usize {
+        self.origin as usize + self.len
+    }
+
+    pub fn overlaps(&self, other: &LoadedImage) -> bool {
+        self.len > 0
+            && other.len > 0
+            && (self.origin as usize) < other.end()
+            && (other.origin as usize) < self.end()
+    }
+}
+
+impl fmt::Display for LoadedImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.len {
+            0 => write!(f, "x{:04X} (empty)", self.origin),
+            len => write!(
+                f,
+                "x{:04X} - x{:04X} ({len} words)",
+                self.origin,
+                self.end() - 1
+            ),
+        }
+    }
+}
+
+/// What to do when an image is loaded over memory an earlier image occupies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverlapPolicy {
+    /// Refuse to load the image; memory is left untouched.
+    #[default]
+    Reject,
+    /// Load the image anyway and list the overwritten images in `LoadedImage::overlaps`.
+    Warn,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    MissingFile(PathBuf),
+    Io(io::Error),
+    /// There isn't even an origin word.
+    Empty,
+    /// Images are made of 16-bit words, so they have an even number of bytes.
+    OddByteCount {
+        len: usize,
+    },
+    /// The image runs past xFFFF.
+    AddressOverflow {
+        origin: u16,
+        len: usize,
+    },
+    /// A `.hex` or `.bin` image that isn't UTF-8 text.
+    NotText,
+    /// A line of a `.hex` or `.bin` image that isn't a single word in that format.
+    InvalidWord {
+        line: usize,
+        text: String,
+    },
+    /// `image` would overwrite part of `existing`.
+    Overlap {
+        existing: LoadedImage,
+        image: LoadedImage,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::MissingFile(path) => write!(f, "{} doesn't exist", path.display()),
+            LoadError::Io(err) => write!(f, "I/O error: {err}"),
+            LoadError::Empty => write!(f, "the image is empty"),
+            LoadError::OddByteCount { len } => {
+                write!(f, "the image has an odd number of bytes ({len})")
+            }
+            LoadError::AddressOverflow { origin, len } => write!(
+                f,
+                "{len} words starting at x{origin:04X} run past the end of memory"
+            ),
+            LoadError::NotText => write!(f, "the image isn't a text file"),
+            LoadError::InvalidWord { line, text } => {
+                write!(f, "line {line}: `{text}` isn't a valid word")
+            }
+            LoadError::Overlap { existing, image } => {
+                write!(f, "image at {image} overlaps image at {existing}")
+            }
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_bytes() {
+        let image = Image::from_bytes(&[0x30, 0x00, 0xf0, 0x25, 0x00, 0x01]).unwrap();
+
+        assert_eq!(image.origin, 0x3000);
+        assert_eq!(image.words, [0xf025, 0x0001]);
+        assert_eq!(image.to_bytes(), [0x30, 0x00, 0xf0, 0x25, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn test_from_bytes_errors() {
+        assert!(matches!(Image::from_bytes(&[]), Err(LoadError::Empty)));
+        assert!(matches!(
+            Image::from_bytes(&[0x30, 0x00, 0xf0]),
+            Err(LoadError::OddByteCount { len: 3 })
+        ));
+        assert!(matches!(
+            Image::from_bytes(&[0xff, 0xff, 0x00, 0x01, 0x00, 0x02]),
+            Err(LoadError::AddressOverflow {
+                origin: 0xffff,
+                len: 2
+            })
+        ));
+        // filling memory right up to xFFFF is fine
+        assert!(Image::from_bytes(&[0xff, 0xff, 0x00, 0x01]).is_ok());
+    }
+
+    #[test]
+    fn test_text_formats() {
+        let hex = b"3000\nF025\n\n00ff\n";
+        let binary = b"0011000000000000\r\n1111000000100101\r\n";
+
+        assert_eq!(ImageFormat::detect(hex), ImageFormat::Hex);
+        assert_eq!(ImageFormat::detect(binary), ImageFormat::Binary);
+        assert_eq!(ImageFormat::detect(&[0x30, 0x00]), ImageFormat::Object);
+
+        let image = Image::parse(hex, ImageFormat::Hex).unwrap();
+        assert_eq!(image.origin, 0x3000);
+        assert_eq!(image.words, [0xf025, 0x00ff]);
+
+        let image = Image::read(&binary[..]).unwrap();
+        assert_eq!(image.origin, 0x3000);
+        assert_eq!(image.words, [0xf025]);
+    }
+
+    #[test]
+    fn test_text_format_errors() {
+        assert!(matches!(
+            Image::parse(b"3000\nF02\n", ImageFormat::Hex),
+            Err(LoadError::InvalidWord { line: 2, .. })
+        ));
+        assert!(matches!(
+            Image::parse(b"\n", ImageFormat::Binary),
+            Err(LoadError::Empty)
+        ));
+        assert_eq!(
+            ImageFormat::from_extension("images/2048.HEX"),
+            Some(ImageFormat::Hex)
+        );
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let image = LoadedImage::new(0x3000, 0x10);
+
+        assert!(image.overlaps(&LoadedImage::new(0x300f, 1)));
+        assert!(image.overlaps(&LoadedImage::new(0x2000, 0x1001)));
+        assert!(!image.overlaps(&LoadedImage::new(0x3010, 5)));
+        assert!(!image.overlaps(&LoadedImage::new(0x3005, 0)));
+    }
+}
@@ -1,4 +1,4 @@
-use super::{get_cond_flag, safe_u16_add, sign_extend, Vm};
+use super::{get_cond_flag, safe_u16_add, Operand, Vm, VmError};
 
 /// ADD takes two values and stores them in a register.
 /// In register mode, the second value to add is found in a register.
@@ -20,28 +20,22 @@ use super::{get_cond_flag, safe_u16_add, sign_extend, Vm};
 /// ┌───────────────┼───────────┼───────────┼───┼───────────────────┐
 /// │      0001     │     DR    │  SR1      │ 1 │       IMM5        │
 /// └───────────────┴───────────┴───────────┴───┴───────────────────┘
-pub fn add(instr: u16, vm: &mut Vm) {
-    let dr = (instr >> 9) & 0x7;
-    let sr1 = (instr >> 6) & 0x7;
-    let imm_flag = (instr >> 5) & 0x1;
-
-    if imm_flag == 1 {
-        let imm5 = sign_extend(instr & 0x1F, 5);
-        let value = safe_u16_add(vm.register.get(sr1), imm5);
-        vm.register.update(dr, value);
-        vm.register.cond = get_cond_flag(value);
-    } else {
-        let sr2 = instr & 0x7;
-        let value = safe_u16_add(vm.register.get(sr1), vm.register.get(sr2));
-
-        vm.register.update(dr, value);
-        vm.register.cond = get_cond_flag(value);
-    }
+pub fn add(dr: u16, sr1: u16, operand: Operand, vm: &mut Vm) -> Result<(), VmError> {
+    let operand = match operand {
+        Operand::Register(sr2) => vm.register.get(sr2)?,
+        Operand::Immediate(imm5) => imm5 as u16,
+    };
+    let value = safe_u16_add(vm.register.get(sr1)?, operand);
+
+    vm.register.update(dr, value)?;
+    vm.register.set_cond(get_cond_flag(value));
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::hardware::instruction::ConditionFlag;
+    use crate::hardware::instruction::{execute_instruction, ConditionFlag};
 
     use super::*;
 
@@ -53,10 +47,10 @@ mod tests {
         vm.register.r2 = 98;
 
         // load r1=4917 and r2=98, then add the values=>5015, then write to r0
-        add(0b_0001_000_001_0_00_010, &mut vm);
+        execute_instruction(0b_0001_000_001_0_00_010, &mut vm).unwrap();
 
         assert_eq!(vm.register.r0, 5015);
-        assert_eq!(vm.register.cond, ConditionFlag::POS as u16);
+        assert_eq!(vm.register.cond(), ConditionFlag::POS as u16);
     }
 
     #[test]
@@ -67,10 +61,10 @@ mod tests {
         vm.register.r2 = 64549; // -987
 
         // load r1=105 and r2=-987, then add the values=>-882 (=64654), then write to r0
-        add(0b_0001_000_001_0_00_010, &mut vm);
+        execute_instruction(0b_0001_000_001_0_00_010, &mut vm).unwrap();
 
         assert_eq!(vm.register.r0, 64654);
-        assert_eq!(vm.register.cond, ConditionFlag::NEG as u16);
+        assert_eq!(vm.register.cond(), ConditionFlag::NEG as u16);
     }
 
     #[test]
@@ -80,9 +74,9 @@ mod tests {
         vm.register.r6 = 16384;
 
         // load r6=16384, then add sr2=29 (=-3), then write result=16381 to r6
-        add(0b_0001_110_110_1_11101, &mut vm);
+        execute_instruction(0b_0001_110_110_1_11101, &mut vm).unwrap();
 
         assert_eq!(vm.register.r6, 16381);
-        assert_eq!(vm.register.cond, ConditionFlag::POS as u16);
+        assert_eq!(vm.register.cond(), ConditionFlag::POS as u16);
     }
 }
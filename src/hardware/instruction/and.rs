@@ -1,4 +1,4 @@
-use super::{get_cond_flag, sign_extend, Vm};
+use super::{get_cond_flag, Operand, Vm, VmError};
 
 /// Your good old logical `and` function. Two operation modes, immediate or passing a register.
 ///
@@ -6,34 +6,28 @@ use super::{get_cond_flag, sign_extend, Vm};
 /// ┌───────────────┼───────────┼───────────┼───┼───────┼───────────┐
 /// │      0101     │     DR    │  SR1      │ 0 │  00   │    SR2    │
 /// └───────────────┴───────────┴───────────┴───┴───────┴───────────┘
-
+///
 ///  15           12│11        9│8         6│ 5 │4                 0
 /// ┌───────────────┼───────────┼───────────┼───┼───────────────────┐
 /// │      0101     │     DR    │  SR1      │ 1 │       IMM5        │
 /// └───────────────┴───────────┴───────────┴───┴───────────────────┘
 ///
-pub fn and(instr: u16, vm: &mut Vm) {
-    let dr = (instr >> 9) & 0x7;
-    let sr1 = (instr >> 6) & 0x7;
-    let imm_flag = (instr >> 5) & 0x1;
+pub fn and(dr: u16, sr1: u16, operand: Operand, vm: &mut Vm) -> Result<(), VmError> {
+    let operand = match operand {
+        Operand::Register(sr2) => vm.register.get(sr2)?,
+        Operand::Immediate(imm5) => imm5 as u16,
+    };
+    let value = vm.register.get(sr1)? & operand;
 
-    if imm_flag == 1 {
-        let imm5 = sign_extend(instr & 0x1F, 5);
-        let value = vm.register.get(sr1) & imm5;
-        vm.register.update(dr, value);
-        vm.register.cond = get_cond_flag(value);
-    } else {
-        let sr2 = instr & 0x7;
-        let value = vm.register.get(sr1) & vm.register.get(sr2);
+    vm.register.update(dr, value)?;
+    vm.register.set_cond(get_cond_flag(value));
 
-        vm.register.update(dr, value);
-        vm.register.cond = get_cond_flag(value);
-    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
-    use crate::hardware::instruction::ConditionFlag;
+    use crate::hardware::instruction::{execute_instruction, ConditionFlag};
 
     use super::*;
 
@@ -45,10 +39,10 @@ mod test {
         vm.register.r2 = 98;
 
         // load r1=105 and r2=-987, then compute bitwise AND result=32, then write to r0
-        and(0b_0001_000_001_0_00_010, &mut vm);
+        execute_instruction(0b_0101_000_001_0_00_010, &mut vm).unwrap();
 
         assert_eq!(vm.register.r0, 32);
-        assert_eq!(vm.register.cond, ConditionFlag::POS as u16);
+        assert_eq!(vm.register.cond(), ConditionFlag::POS as u16);
     }
 
     #[test]
@@ -58,9 +52,9 @@ mod test {
         vm.register.r1 = 105;
 
         // load r1=105, then add sr2=7, then write result=1 to r0
-        and(0b_0001_000_001_1_00111, &mut vm);
+        execute_instruction(0b_0101_000_001_1_00111, &mut vm).unwrap();
 
         assert_eq!(vm.register.r0, 1);
-        assert_eq!(vm.register.cond, ConditionFlag::POS as u16);
+        assert_eq!(vm.register.cond(), ConditionFlag::POS as u16);
     }
 }
@@ -1,4 +1,4 @@
-use super::{safe_u16_add, sign_extend, Vm};
+use super::{safe_u16_add, Vm, VmError};
 
 /// The branching operation; means to go somewhere else in the assembly code
 /// depending on whether some conditions are met.
@@ -13,17 +13,19 @@ use super::{safe_u16_add, sign_extend, Vm};
 /// │      0000     │ N │ Z │ P │             PCOffset9             │
 /// └───────────────┴───┴───┴───┴───────────────────────────────────┘
 ///
-pub fn br(instr: u16, vm: &mut Vm) {
-    let cond_flag = (instr >> 9) & 0x7;
-    let pc_offset9 = sign_extend(instr & 0x1ff, 9);
+pub fn br(n: bool, z: bool, p: bool, offset: i16, vm: &mut Vm) -> Result<(), VmError> {
+    let cond_flag = (n as u16) << 2 | (z as u16) << 1 | p as u16;
 
-    if (vm.register.cond & cond_flag) != 0 {
-        vm.register.pc = safe_u16_add(vm.register.pc, pc_offset9);
+    if (vm.register.cond() & cond_flag) != 0 {
+        vm.register.pc = safe_u16_add(vm.register.pc, offset as u16);
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
+    use crate::hardware::instruction::execute_instruction;
 
     use super::*;
 
@@ -32,10 +34,10 @@ mod test {
         let mut vm = Vm::new();
 
         vm.register.pc = 97;
-        vm.register.cond = 4;
+        vm.register.set_cond(4);
 
         // load condition flag = 4 (=NEG), then compare to cond=4, then load pc=97, then add pc_offset9=107, save result=204 to pc
-        br(0b0000_1_0_0_001101011, &mut vm);
+        execute_instruction(0b0000_1_0_0_001101011, &mut vm).unwrap();
 
         assert_eq!(vm.register.pc, 204);
     }
@@ -45,10 +47,10 @@ mod test {
         let mut vm = Vm::new();
 
         vm.register.pc = 97;
-        vm.register.cond = 2;
+        vm.register.set_cond(2);
 
         // load condition flag = 4 (=NEG), then compare to cond=2
-        br(0b0000_1_0_0_001101011, &mut vm);
+        execute_instruction(0b0000_1_0_0_001101011, &mut vm).unwrap();
 
         assert_eq!(vm.register.pc, 97);
     }
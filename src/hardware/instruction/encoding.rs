@@ -0,0 +1,400 @@
+use super::{get_op_code, sign_extend, OpCode};
+
+/// An instruction word split into its fields, e.g. `Add { dr: 0, sr1: 1, operand: Immediate(-3), .. }`
+/// for `ADD R0, R1, #-3`. Registers are their index, offsets and immediates are sign-extended.
+///
+/// Every word decodes to an instruction and encodes back to the same word, including the bits the
+/// hardware ignores, e.g. bits 4:3 of an ADD in register mode. Those are kept in `unused`,
+/// where they are in the word, and are 0 in everything the assembler writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// `unused` is bits 4:3 in register mode, always 0 in immediate mode.
+    Add {
+        dr: u16,
+        sr1: u16,
+        operand: Operand,
+        unused: u16,
+    },
+    /// `unused` is bits 4:3 in register mode, always 0 in immediate mode.
+    And {
+        dr: u16,
+        sr1: u16,
+        operand: Operand,
+        unused: u16,
+    },
+    /// Doesn't branch when no condition is tested.
+    Br {
+        n: bool,
+        z: bool,
+        p: bool,
+        offset: i16,
+    },
+    /// `RET` when `base` is R7. `unused` is bits 11:9 and 5:0.
+    Jmp {
+        base: u16,
+        unused: u16,
+    },
+    Jsr {
+        offset: i16,
+    },
+    /// `unused` is bits 10:9 and 5:0.
+    Jsrr {
+        base: u16,
+        unused: u16,
+    },
+    Ld {
+        dr: u16,
+        offset: i16,
+    },
+    Ldi {
+        dr: u16,
+        offset: i16,
+    },
+    Ldr {
+        dr: u16,
+        base: u16,
+        offset: i16,
+    },
+    Lea {
+        dr: u16,
+        offset: i16,
+    },
+    /// `unused` is bits 5:0 flipped, as the assembler sets them all.
+    Not {
+        dr: u16,
+        sr: u16,
+        unused: u16,
+    },
+    /// `unused` is bits 11:0.
+    Rti {
+        unused: u16,
+    },
+    St {
+        sr: u16,
+        offset: i16,
+    },
+    Sti {
+        sr: u16,
+        offset: i16,
+    },
+    Str {
+        sr: u16,
+        base: u16,
+        offset: i16,
+    },
+    /// `unused` is bits 11:8.
+    Trap {
+        vector: u8,
+        unused: u16,
+    },
+    /// Opcode 1101, which isn't an instruction. `unused` is bits 11:0.
+    Reserved {
+        unused: u16,
+    },
+}
+
+/// The second value of ADD and AND.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    /// SR2
+    Register(u16),
+    /// imm5
+    Immediate(i16),
+}
+
+impl Instruction {
+    pub fn decode(word: u16) -> Self {
+        let register = |shift: u16| (word >> shift) & 0x7;
+        let offset = |bits: u8| sign_extend(word & ((1 << bits) - 1), bits) as i16;
+        let (dr, sr1) = (register(9), register(6));
+        let alu = || match word & 0x20 {
+            0 => (Operand::Register(register(0)), word & 0x18),
+            _ => (Operand::Immediate(offset(5)), 0),
+        };
+
+        let Some(op_code) = get_op_code(word) else {
+            unreachable!("every 4 bit opcode is an OpCode")
+        };
+        match op_code {
+            OpCode::BR => Instruction::Br {
+                n: word & 0x800 != 0,
+                z: word & 0x400 != 0,
+                p: word & 0x200 != 0,
+                offset: offset(9),
+            },
+            OpCode::ADD => {
+                let (operand, unused) = alu();
+                Instruction::Add {
+                    dr,
+                    sr1,
+                    operand,
+                    unused,
+                }
+            }
+            OpCode::AND => {
+                let (operand, unused) = alu();
+                Instruction::And {
+                    dr,
+                    sr1,
+                    operand,
+                    unused,
+                }
+            }
+            OpCode::LD => Instruction::Ld {
+                dr,
+                offset: offset(9),
+            },
+            OpCode::LDI => Instruction::Ldi {
+                dr,
+                offset: offset(9),
+            },
+            OpCode::LEA => Instruction::Lea {
+                dr,
+                offset: offset(9),
+            },
+            OpCode::ST => Instruction::St {
+                sr: dr,
+                offset: offset(9),
+            },
+            OpCode::STI => Instruction::Sti {
+                sr: dr,
+                offset: offset(9),
+            },
+            OpCode::LDR => Instruction::Ldr {
+                dr,
+                base: sr1,
+                offset: offset(6),
+            },
+            OpCode::STR => Instruction::Str {
+                sr: dr,
+                base: sr1,
+                offset: offset(6),
+            },
+            OpCode::JSR if word & 0x800 != 0 => Instruction::Jsr { offset: offset(11) },
+            OpCode::JSR => Instruction::Jsrr {
+                base: sr1,
+                unused: word & 0x63f,
+            },
+            OpCode::NOT => Instruction::Not {
+                dr,
+                sr: sr1,
+                unused: word & 0x3f ^ 0x3f,
+            },
+            OpCode::JMP => Instruction::Jmp {
+                base: sr1,
+                unused: word & 0xe3f,
+            },
+            OpCode::RTI => Instruction::Rti {
+                unused: word & 0xfff,
+            },
+            OpCode::TRAP => Instruction::Trap {
+                vector: word as u8,
+                unused: word & 0xf00,
+            },
+            OpCode::RES => Instruction::Reserved {
+                unused: word & 0xfff,
+            },
+        }
+    }
+
+    /// The instruction word. Fields are cut to their width, e.g. an offset of 256 in a BR
+    /// becomes -256.
+    pub fn encode(self) -> u16 {
+        let op_code = |op_code: OpCode| (op_code as u16) << 12;
+        let register = |index: u16, shift: u16| (index & 0x7) << shift;
+        let offset = |offset: i16, bits: u16| offset as u16 & ((1 << bits) - 1);
+        let alu = |dr, sr1, operand, unused: u16| {
+            let operand = match operand {
+                Operand::Register(sr2) => register(sr2, 0) | unused & 0x18,
+                Operand::Immediate(imm5) => 0x20 | offset(imm5, 5),
+            };
+            register(dr, 9) | register(sr1, 6) | operand
+        };
+
+        match self {
+            Instruction::Add {
+                dr,
+                sr1,
+                operand,
+                unused,
+            } => op_code(OpCode::ADD) | alu(dr, sr1, operand, unused),
+            Instruction::And {
+                dr,
+                sr1,
+                operand,
+                unused,
+            } => op_code(OpCode::AND) | alu(dr, sr1, operand, unused),
+            Instruction::Br {
+                n,
+                z,
+                p,
+                offset: pc_offset9,
+            } => {
+                op_code(OpCode::BR)
+                    | (n as u16) << 11
+                    | (z as u16) << 10
+                    | (p as u16) << 9
+                    | offset(pc_offset9, 9)
+            }
+            Instruction::Jmp { base, unused } => {
+                op_code(OpCode::JMP) | register(base, 6) | unused & 0xe3f
+            }
+            Instruction::Jsr {
+                offset: pc_offset11,
+            } => op_code(OpCode::JSR) | 0x800 | offset(pc_offset11, 11),
+            Instruction::Jsrr { base, unused } => {
+                op_code(OpCode::JSR) | register(base, 6) | unused & 0x63f
+            }
+            Instruction::Ld {
+                dr,
+                offset: pc_offset9,
+            } => op_code(OpCode::LD) | register(dr, 9) | offset(pc_offset9, 9),
+            Instruction::Ldi {
+                dr,
+                offset: pc_offset9,
+            } => op_code(OpCode::LDI) | register(dr, 9) | offset(pc_offset9, 9),
+            Instruction::Ldr {
+                dr,
+                base,
+                offset: offset6,
+            } => op_code(OpCode::LDR) | register(dr, 9) | register(base, 6) | offset(offset6, 6),
+            Instruction::Lea {
+                dr,
+                offset: pc_offset9,
+            } => op_code(OpCode::LEA) | register(dr, 9) | offset(pc_offset9, 9),
+            Instruction::Not { dr, sr, unused } => {
+                op_code(OpCode::NOT) | register(dr, 9) | register(sr, 6) | (unused & 0x3f ^ 0x3f)
+            }
+            Instruction::Rti { unused } => op_code(OpCode::RTI) | unused & 0xfff,
+            Instruction::St {
+                sr,
+                offset: pc_offset9,
+            } => op_code(OpCode::ST) | register(sr, 9) | offset(pc_offset9, 9),
+            Instruction::Sti {
+                sr,
+                offset: pc_offset9,
+            } => op_code(OpCode::STI) | register(sr, 9) | offset(pc_offset9, 9),
+            Instruction::Str {
+                sr,
+                base,
+                offset: offset6,
+            } => op_code(OpCode::STR) | register(sr, 9) | register(base, 6) | offset(offset6, 6),
+            Instruction::Trap { vector, unused } => {
+                op_code(OpCode::TRAP) | unused & 0xf00 | vector as u16
+            }
+            Instruction::Reserved { unused } => op_code(OpCode::RES) | unused & 0xfff,
+        }
+    }
+
+    /// The PCoffset of the instructions that address memory, or jump, relative to the incremented PC.
+    pub fn pc_offset(self) -> Option<i16> {
+        match self {
+            Instruction::Br { offset, .. }
+            | Instruction::Jsr { offset }
+            | Instruction::Ld { offset, .. }
+            | Instruction::Ldi { offset, .. }
+            | Instruction::Lea { offset, .. }
+            | Instruction::St { offset, .. }
+            | Instruction::Sti { offset, .. } => Some(offset),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            Instruction::decode(0x127f),
+            Instruction::Add {
+                dr: 1,
+                sr1: 1,
+                operand: Operand::Immediate(-1),
+                unused: 0,
+            }
+        );
+        assert_eq!(
+            Instruction::decode(0x5483),
+            Instruction::And {
+                dr: 2,
+                sr1: 2,
+                operand: Operand::Register(3),
+                unused: 0,
+            }
+        );
+        assert_eq!(
+            Instruction::decode(0x0dfd),
+            Instruction::Br {
+                n: true,
+                z: true,
+                p: false,
+                offset: -3,
+            }
+        );
+        assert_eq!(Instruction::decode(0x4803), Instruction::Jsr { offset: 3 });
+        assert_eq!(
+            Instruction::decode(0x6ba0),
+            Instruction::Ldr {
+                dr: 5,
+                base: 6,
+                offset: -32,
+            }
+        );
+        assert_eq!(
+            Instruction::decode(0x973f),
+            Instruction::Not {
+                dr: 3,
+                sr: 4,
+                unused: 0,
+            }
+        );
+        assert_eq!(
+            Instruction::decode(0xf025),
+            Instruction::Trap {
+                vector: 0x25,
+                unused: 0,
+            }
+        );
+
+        // bits the hardware ignores
+        assert_eq!(
+            Instruction::decode(0x1018),
+            Instruction::Add {
+                dr: 0,
+                sr1: 0,
+                operand: Operand::Register(0),
+                unused: 0x18,
+            }
+        );
+        assert_eq!(
+            Instruction::decode(0xc1ff),
+            Instruction::Jmp {
+                base: 7,
+                unused: 0x3f,
+            }
+        );
+        assert_eq!(
+            Instruction::decode(0x9000),
+            Instruction::Not {
+                dr: 0,
+                sr: 0,
+                unused: 0x3f,
+            }
+        );
+        assert_eq!(
+            Instruction::decode(0xd123),
+            Instruction::Reserved { unused: 0x123 }
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for word in 0..=u16::MAX {
+            let instruction = Instruction::decode(word);
+            assert_eq!(instruction.encode(), word, "{instruction:?}");
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::Vm;
+use super::{Vm, VmError};
 
 /// The program unconditionally jumps to the location specified by the contents of the base register.
 /// Bits [8:6] identify the base register. `RET` is listed as a separate instruction
@@ -14,13 +14,15 @@ use super::Vm;
 /// │      1100     │    000    │    111    │       000000          │
 /// └───────────────┴───────────┴───────────┴───────────────────────┘
 ///
-pub fn jmp(instr: u16, vm: &mut Vm) {
-    let r1 = (instr >> 6) & 0x7;
-    vm.register.pc = vm.register.get(r1);
+pub fn jmp(base: u16, vm: &mut Vm) -> Result<(), VmError> {
+    vm.register.pc = vm.register.get(base)?;
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
+    use crate::hardware::instruction::execute_instruction;
 
     use super::*;
 
@@ -31,7 +33,7 @@ mod test {
         vm.register.r5 = 16;
 
         // load r5=16, then write to pc
-        jmp(0b1100_000_101_000000, &mut vm);
+        execute_instruction(0b1100_000_101_000000, &mut vm).unwrap();
 
         assert_eq!(vm.register.pc, 16);
     }
@@ -1,4 +1,4 @@
-use super::{safe_u16_add, sign_extend, Vm};
+use super::{safe_u16_add, Vm, VmError};
 
 /// First, the incremented PC is saved in R7.
 /// This is the linkage back to the calling routine.
@@ -16,23 +16,31 @@ use super::{safe_u16_add, sign_extend, Vm};
 /// │      0100     │ 0 │   00  │ BaseR │           000000          │
 /// └───────────────┴───┴───────┴───────┴───────────────────────────┘
 ///
-pub fn jsr(instr: u16, vm: &mut Vm) {
-    let long_flag = (instr >> 11) & 1;
+pub fn jsr(offset: i16, vm: &mut Vm) -> Result<(), VmError> {
+    let target = safe_u16_add(vm.register.pc, offset as u16);
+    jump_to_subroutine(target, vm);
 
+    Ok(())
+}
+
+/// JSRR, with the subroutine's address in `base`.
+pub fn jsrr(base: u16, vm: &mut Vm) -> Result<(), VmError> {
+    // the base register is read before R7 is overwritten so `JSRR R7` works
+    let target = vm.register.get(base)?;
+    jump_to_subroutine(target, vm);
+
+    Ok(())
+}
+
+fn jump_to_subroutine(target: u16, vm: &mut Vm) {
     vm.register.r7 = vm.register.pc;
-    if long_flag == 1 {
-        // JSR
-        let pc_offset11 = sign_extend(instr & 0x7ff, 11);
-        vm.register.pc = safe_u16_add(vm.register.pc, pc_offset11);
-    } else {
-        // JSRR
-        let sr1 = (instr >> 6) & 0x7;
-        vm.register.pc = sr1;
-    }
+    vm.register.pc = target;
 }
 
 #[cfg(test)]
 mod test {
+    use crate::hardware::instruction::execute_instruction;
+
     use super::*;
 
     #[test]
@@ -42,7 +50,7 @@ mod test {
         vm.register.pc = 98; // write 98 to pc
 
         // load pc=98, then write to r7, then add pc_offset11=860, write result=958 to pc
-        jsr(0b_0100_1_01101011100, &mut vm);
+        execute_instruction(0b_0100_1_01101011100, &mut vm).unwrap();
 
         assert_eq!(vm.register.pc, 958);
         assert_eq!(vm.register.r7, 98);
@@ -56,8 +64,9 @@ mod test {
         vm.register.r3 = 1092;
 
         // load pc=98, then write to r7, then load r3=1092, then write r3=1092 to pc
-        jsr(0b_0100_0_00_011_000000, &mut vm);
+        execute_instruction(0b_0100_0_00_011_000000, &mut vm).unwrap();
 
-        assert_eq!(vm.register.r3, 1092);
+        assert_eq!(vm.register.pc, 1092);
+        assert_eq!(vm.register.r7, 98);
     }
 }
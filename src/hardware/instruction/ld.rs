@@ -1,4 +1,4 @@
-use super::{get_cond_flag, safe_u16_add, sign_extend, Vm};
+use super::{get_cond_flag, safe_u16_add, Vm, VmError};
 
 /// An address is computed by sign-extending bits [8:0] to 16 bits and
 /// adding this value to the incremented PC.
@@ -10,21 +10,18 @@ use super::{get_cond_flag, safe_u16_add, sign_extend, Vm};
 /// │      0010     │     DR    │            PCOffset9              │
 /// └───────────────┴───────────┴───────────────────────────────────┘
 ///
-pub fn ld(instr: u16, vm: &mut Vm) {
-    let pc_offset9 = sign_extend(instr & 0x1ff, 9);
-    let dr = (instr >> 9) & 0x7;
-
-    let addr = safe_u16_add(vm.register.pc, pc_offset9);
-    let value = vm.memory.read(addr);
-
-    vm.register.update(dr, value);
-    vm.register.cond = get_cond_flag(value);
+pub fn ld(dr: u16, offset: i16, vm: &mut Vm) -> Result<(), VmError> {
+    let addr = safe_u16_add(vm.register.pc, offset as u16);
+    let value = vm.read_memory(addr)?;
+    vm.register.update(dr, value)?;
+    vm.register.set_cond(get_cond_flag(value));
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
 
-    use crate::hardware::instruction::ConditionFlag;
+    use crate::hardware::instruction::{execute_instruction, ConditionFlag};
 
     use super::*;
 
@@ -36,9 +33,9 @@ mod test {
         vm.register.pc = 35;
 
         // load pc=35, then add pc_offset9=34, then load memory at addr=69, then write 132 to r3
-        ld(0b0010_011_000100010, &mut vm);
+        execute_instruction(0b0010_011_000100010, &mut vm).unwrap();
 
         assert_eq!(vm.register.r3, 132);
-        assert_eq!(vm.register.cond, ConditionFlag::POS as u16);
+        assert_eq!(vm.register.cond(), ConditionFlag::POS as u16);
     }
 }
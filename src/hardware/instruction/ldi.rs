@@ -1,4 +1,4 @@
-use super::{get_cond_flag, sign_extend, Vm};
+use super::{get_cond_flag, safe_u16_add, Vm, VmError};
 
 /// Load indirect
 /// An address is computed by sign-extending bits [8:0] to 16 bits and adding this
@@ -13,21 +13,18 @@ use super::{get_cond_flag, sign_extend, Vm};
 /// │      1010     │     DR    │               PCOffset9           │
 /// └───────────────┴───────────┴───────────────────────────────────┘
 ///
-pub fn ldi(instr: u16, vm: &mut Vm) {
-    let dr = (instr >> 9) & 0x7;
-    let pc_offset9 = sign_extend(instr & 0x1ff, 9);
-
-    let first_read_addr = vm.register.pc + pc_offset9;
-    let addr = vm.memory.read(first_read_addr);
-    let value = vm.memory.read(addr);
-
-    vm.register.update(dr, value);
-    vm.register.cond = get_cond_flag(value);
+pub fn ldi(dr: u16, offset: i16, vm: &mut Vm) -> Result<(), VmError> {
+    let first_read_addr = safe_u16_add(vm.register.pc, offset as u16);
+    let addr = vm.read_memory(first_read_addr)?;
+    let value = vm.read_memory(addr)?;
+    vm.register.update(dr, value)?;
+    vm.register.set_cond(get_cond_flag(value));
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
-    use crate::hardware::instruction::ConditionFlag;
+    use crate::hardware::instruction::{execute_instruction, ConditionFlag};
 
     use super::*;
 
@@ -40,9 +37,9 @@ mod test {
         vm.memory.write(458, 101);
 
         // load memory at addr = pc+33, then save to r3
-        ldi(0b1010_011_000100001, &mut vm);
+        execute_instruction(0b1010_011_000100001, &mut vm).unwrap();
 
         assert_eq!(vm.register.r3, 101);
-        assert_eq!(vm.register.cond, ConditionFlag::POS as u16);
+        assert_eq!(vm.register.cond(), ConditionFlag::POS as u16);
     }
 }
@@ -1,4 +1,4 @@
-use super::{get_cond_flag, safe_u16_add, sign_extend, Vm};
+use super::{get_cond_flag, safe_u16_add, Vm, VmError};
 
 /// Load base + offset
 /// An address is computed by sign-extending bits [5:0] to 16 bits
@@ -8,24 +8,22 @@ use super::{get_cond_flag, safe_u16_add, sign_extend, Vm};
 ///
 ///  15           12│11        9│8             6│5                 0
 /// ┌───────────────┼───────────┼───────────────┼───────────────────┐
-/// │      1010     │     DR    │     BaseR     │       Offset6     │
+/// │      0110     │     DR    │     BaseR     │       Offset6     │
 /// └───────────────┴───────────┴───────────────┴───────────────────┘
 ///
-pub fn ldr(instr: u16, vm: &mut Vm) {
-    let offset6 = sign_extend(instr & 0x3f, 6);
-    let sr = (instr >> 6) & 0x7;
-    let dr = (instr >> 9) & 0x7;
-
-    let addr = safe_u16_add(vm.register.get(sr), offset6);
-    let value = vm.memory.read(addr);
-    vm.register.update(dr, value);
-    vm.register.cond = get_cond_flag(value);
+pub fn ldr(dr: u16, base: u16, offset: i16, vm: &mut Vm) -> Result<(), VmError> {
+    let addr = safe_u16_add(vm.register.get(base)?, offset as u16);
+    let value = vm.read_memory(addr)?;
+    vm.register.update(dr, value)?;
+    vm.register.set_cond(get_cond_flag(value));
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
 
-    use crate::hardware::instruction::ConditionFlag;
+    use crate::hardware::instruction::{execute_instruction, ConditionFlag};
 
     use super::*;
 
@@ -37,11 +35,11 @@ mod test {
         vm.register.r1 = 35;
 
         // load r1=35, then add offset6=18, then load memory at addr=53, then write 132 to r3
-        ldr(0b1010_011_001_010010, &mut vm);
+        execute_instruction(0b0110_011_001_010010, &mut vm).unwrap();
 
         println!("{:?}", vm.register);
 
         assert_eq!(vm.register.r3, 132);
-        assert_eq!(vm.register.cond, ConditionFlag::POS as u16);
+        assert_eq!(vm.register.cond(), ConditionFlag::POS as u16);
     }
 }
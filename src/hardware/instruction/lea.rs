@@ -1,4 +1,4 @@
-use super::{get_cond_flag, safe_u16_add, sign_extend, Vm};
+use super::{get_cond_flag, safe_u16_add, Vm, VmError};
 
 /// An address is computed by sign-extending bits [8:0] to 16 bits and adding
 /// this value to the incremented PC.
@@ -9,19 +9,17 @@ use super::{get_cond_flag, safe_u16_add, sign_extend, Vm};
 /// ┌───────────────┼───────────┼───────────────────────────────────┐
 /// │      1110     │     DR    │            PCOffset9              │
 /// └───────────────┴───────────┴───────────────────────────────────┘
-pub fn lea(instr: u16, vm: &mut Vm) {
-    let pc_offset9 = sign_extend(instr & 0x1ff, 9);
-    let dr = (instr >> 9) & 0x7;
-
-    let value = safe_u16_add(vm.register.pc, pc_offset9);
-    vm.register.update(dr, value);
-    vm.register.cond = get_cond_flag(value);
+pub fn lea(dr: u16, offset: i16, vm: &mut Vm) -> Result<(), VmError> {
+    let value = safe_u16_add(vm.register.pc, offset as u16);
+    vm.register.update(dr, value)?;
+    vm.register.set_cond(get_cond_flag(value));
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
 
-    use crate::hardware::instruction::ConditionFlag;
+    use crate::hardware::instruction::{execute_instruction, ConditionFlag};
 
     use super::*;
 
@@ -32,9 +30,9 @@ mod test {
         vm.register.pc = 17;
 
         // compute pc+pc_offset9=17+13=30, then write to r2
-        lea(0b1110_010_000001101, &mut vm);
+        execute_instruction(0b1110_010_000001101, &mut vm).unwrap();
 
         assert_eq!(vm.register.r2, 30);
-        assert_eq!(vm.register.cond, ConditionFlag::POS as u16);
+        assert_eq!(vm.register.cond(), ConditionFlag::POS as u16);
     }
 }
@@ -1,15 +1,16 @@
-use super::Vm;
+use super::{error::VmError, StepOutcome, Vm};
 
 use add::add;
 use and::and;
 use br::br;
 use jmp::jmp;
-use jsr::jsr;
+use jsr::{jsr, jsrr};
 use ld::ld;
 use ldi::ldi;
 use ldr::ldr;
 use lea::lea;
 use not::not;
+use rti::rti;
 use st::st;
 use sti::sti;
 use str::str;
@@ -18,6 +19,7 @@ use trap::trap;
 mod add;
 mod and;
 mod br;
+mod encoding;
 mod jmp;
 mod jsr;
 mod ld;
@@ -25,11 +27,15 @@ mod ldi;
 mod ldr;
 mod lea;
 mod not;
+mod rti;
 mod st;
 mod sti;
 mod str;
 mod trap;
 
+pub use encoding::{Instruction, Operand};
+pub use trap::{trap_alias, TrapHandler, TrapMode};
+
 pub enum ConditionFlag {
     POS = 1 << 0,
     ZRO = 1 << 1,
@@ -45,7 +51,7 @@ pub enum OpCode {
     AND,    // bitwise and
     LDR,    // load register
     STR,    // store register
-    RTI,    // unused
+    RTI,    // return from interrupt
     NOT,    // bitwise not
     LDI,    // load indirect
     STI,    // store indirect
@@ -87,28 +93,35 @@ pub fn get_op_code(instr: u16) -> Option<OpCode> {
     }
 }
 
-pub fn execute_instruction(instr: u16, vm: &mut Vm) {
-    let op_code = get_op_code(instr);
-
-    match op_code {
-        Some(OpCode::BR) => br(instr, vm),
-        Some(OpCode::ADD) => add(instr, vm),
-        Some(OpCode::LD) => ld(instr, vm),
-        Some(OpCode::ST) => st(instr, vm),
-        Some(OpCode::JSR) => jsr(instr, vm),
-        Some(OpCode::AND) => and(instr, vm),
-        Some(OpCode::LDR) => ldr(instr, vm),
-        Some(OpCode::STR) => str(instr, vm),
-        Some(OpCode::RTI) => panic!(),
-        Some(OpCode::NOT) => not(instr, vm),
-        Some(OpCode::LDI) => ldi(instr, vm),
-        Some(OpCode::STI) => sti(instr, vm),
-        Some(OpCode::JMP) => jmp(instr, vm),
-        Some(OpCode::RES) => panic!(),
-        Some(OpCode::LEA) => lea(instr, vm),
-        Some(OpCode::TRAP) => trap(instr, vm),
-        _ => {}
+/// Executes `instr`, assuming the PC has already been incremented past it.
+pub fn execute_instruction(instr: u16, vm: &mut Vm) -> Result<StepOutcome, VmError> {
+    let pc = vm.register.pc.wrapping_sub(1);
+
+    match Instruction::decode(instr) {
+        Instruction::Br { n, z, p, offset } => br(n, z, p, offset, vm)?,
+        Instruction::Add {
+            dr, sr1, operand, ..
+        } => add(dr, sr1, operand, vm)?,
+        Instruction::Ld { dr, offset } => ld(dr, offset, vm)?,
+        Instruction::St { sr, offset } => st(sr, offset, vm)?,
+        Instruction::Jsr { offset } => jsr(offset, vm)?,
+        Instruction::Jsrr { base, .. } => jsrr(base, vm)?,
+        Instruction::And {
+            dr, sr1, operand, ..
+        } => and(dr, sr1, operand, vm)?,
+        Instruction::Ldr { dr, base, offset } => ldr(dr, base, offset, vm)?,
+        Instruction::Str { sr, base, offset } => str(sr, base, offset, vm)?,
+        Instruction::Rti { .. } => rti(instr, vm)?,
+        Instruction::Not { dr, sr, .. } => not(dr, sr, vm)?,
+        Instruction::Ldi { dr, offset } => ldi(dr, offset, vm)?,
+        Instruction::Sti { sr, offset } => sti(sr, offset, vm)?,
+        Instruction::Jmp { base, .. } => jmp(base, vm)?,
+        Instruction::Reserved { .. } => return Err(VmError::IllegalOpcode { pc, instr }),
+        Instruction::Lea { dr, offset } => lea(dr, offset, vm)?,
+        Instruction::Trap { vector, .. } => return trap(vector, vm),
     }
+
+    Ok(StepOutcome::Continue)
 }
 
 pub fn sign_extend(x: u16, bit_count: u8) -> u16 {
@@ -129,3 +142,34 @@ pub fn get_2bytes_chars(x: u16) -> [char; 2] {
 
     [c1, c2] // big endian
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reserved_opcode() {
+        let mut vm = Vm::new();
+
+        vm.register.pc = 0x3001;
+
+        let result = execute_instruction(0b1101_0000_0000_0000, &mut vm);
+
+        assert!(matches!(
+            result,
+            Err(VmError::IllegalOpcode {
+                pc: 0x3000,
+                instr: 0xD000
+            })
+        ));
+    }
+
+    #[test]
+    fn test_unknown_trap() {
+        let mut vm = Vm::new();
+
+        let result = execute_instruction(0xF0FF, &mut vm);
+
+        assert!(matches!(result, Err(VmError::UnknownTrap { vector: 0xFF })));
+    }
+}
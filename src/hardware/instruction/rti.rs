@@ -0,0 +1,89 @@
+use super::{Vm, VmError};
+
+/// Return from interrupt. Only allowed in supervisor mode.
+/// The PC and then the PSR are popped off the supervisor stack (R6).
+/// If the restored PSR is in user mode, the supervisor stack pointer is saved
+/// and R6 is switched back to the user stack.
+/// In user mode a privilege mode violation is raised instead.
+///
+///  15           12│11                                            0
+/// ┌───────────────┼───────────────────────────────────────────────┐
+/// │      1000     │                 000000000000                  │
+/// └───────────────┴───────────────────────────────────────────────┘
+///
+pub fn rti(instr: u16, vm: &mut Vm) -> Result<(), VmError> {
+    if vm.register.is_user_mode() {
+        let pc = vm.register.pc.wrapping_sub(1);
+        return Err(VmError::PrivilegeModeViolation { pc, instr });
+    }
+
+    let mut sp = vm.register.r6;
+    let pc = vm.read_memory(sp)?;
+    sp = sp.wrapping_add(1);
+    let psr = vm.read_memory(sp)?;
+    sp = sp.wrapping_add(1);
+
+    vm.register.pc = pc;
+    vm.register.psr = psr;
+    vm.register.r6 = sp;
+
+    if vm.register.is_user_mode() {
+        vm.register.saved_ssp = sp;
+        vm.register.r6 = vm.register.saved_usp;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_return_to_user_mode() {
+        let mut vm = Vm::new();
+
+        vm.register.psr = 0x0400; // supervisor mode, priority 4
+        vm.register.r6 = 0x2ffe;
+        vm.register.saved_usp = 0xfd00;
+        vm.memory.write(0x2ffe, 0x3042); // PC
+        vm.memory.write(0x2fff, 0x8001); // PSR: user mode, P set
+
+        rti(0b1000_0000_0000_0000, &mut vm).unwrap();
+
+        assert_eq!(vm.register.pc, 0x3042);
+        assert_eq!(vm.register.psr, 0x8001);
+        assert_eq!(vm.register.r6, 0xfd00);
+        assert_eq!(vm.register.saved_ssp, 0x3000);
+    }
+
+    #[test]
+    fn test_return_to_supervisor_mode() {
+        let mut vm = Vm::new();
+
+        vm.register.psr = 0x0700;
+        vm.register.r6 = 0x2ff0;
+        vm.memory.write(0x2ff0, 0x0520);
+        vm.memory.write(0x2ff1, 0x0200);
+
+        rti(0b1000_0000_0000_0000, &mut vm).unwrap();
+
+        assert_eq!(vm.register.pc, 0x0520);
+        assert_eq!(vm.register.priority(), 2);
+        assert_eq!(vm.register.r6, 0x2ff2);
+    }
+
+    #[test]
+    fn test_user_mode() {
+        let mut vm = Vm::new();
+
+        vm.register.pc = 0x3001;
+
+        let result = rti(0b1000_0000_0000_0000, &mut vm);
+
+        assert!(matches!(
+            result,
+            Err(VmError::PrivilegeModeViolation { pc: 0x3000, .. })
+        ));
+    }
+}
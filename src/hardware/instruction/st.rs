@@ -1,4 +1,4 @@
-use super::{safe_u16_add, sign_extend, Vm};
+use super::{safe_u16_add, Vm, VmError};
 
 /// The contents of the register specified by SR are stored in the memory location
 /// whose address is computed by sign-extending bits [8:0] to 16 bits and adding
@@ -8,17 +8,16 @@ use super::{safe_u16_add, sign_extend, Vm};
 /// ┌───────────────┼───────────┼───────────────────────────────────┐
 /// │      0011     │     SR    │            PCOffset9              │
 /// └───────────────┴───────────┴───────────────────────────────────┘
-pub fn st(instr: u16, vm: &mut Vm) {
-    let pc_offset9 = sign_extend(instr & 0x1ff, 9);
-    let sr = (instr >> 9) & 0x7;
-
-    let value = vm.register.get(sr);
-    let addr = safe_u16_add(vm.register.pc, pc_offset9);
-    vm.memory.write(addr, value);
+pub fn st(sr: u16, offset: i16, vm: &mut Vm) -> Result<(), VmError> {
+    let value = vm.register.get(sr)?;
+    let addr = safe_u16_add(vm.register.pc, offset as u16);
+    vm.write_memory(addr, value)?;
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
+    use crate::hardware::instruction::execute_instruction;
 
     use super::*;
 
@@ -30,7 +29,7 @@ mod test {
         vm.register.r2 = 8901;
 
         // load r2=8901, then write to memory at pc+pc_offset6=17+157=174
-        st(0b0011_010_010011101, &mut vm);
+        execute_instruction(0b0011_010_010011101, &mut vm).unwrap();
 
         assert_eq!(vm.memory.read(174), 8901);
     }
@@ -1,4 +1,4 @@
-use super::{safe_u16_add, sign_extend, Vm};
+use super::{safe_u16_add, Vm, VmError};
 
 /// The contents of the register specified by SR are stored in the memory location
 /// whose address is obtained as follows: Bits [8:0] are sign-extended to 16 bits and added to the incremented PC.
@@ -8,18 +8,16 @@ use super::{safe_u16_add, sign_extend, Vm};
 /// ┌───────────────┼───────────┼───────────────────────────────────┐
 /// │      1011     │     SR    │            PCOffset9              │
 /// └───────────────┴───────────┴───────────────────────────────────┘
-pub fn sti(instr: u16, vm: &mut Vm) {
-    let pc_offset9 = sign_extend(instr & 0x1ff, 9);
-    let sr = (instr >> 9) & 0x7;
-
-    let value = vm.register.get(sr);
-    let addr = vm.memory.read(safe_u16_add(vm.register.pc, pc_offset9));
-
-    vm.memory.write(addr, value);
+pub fn sti(sr: u16, offset: i16, vm: &mut Vm) -> Result<(), VmError> {
+    let value = vm.register.get(sr)?;
+    let addr = vm.read_memory(safe_u16_add(vm.register.pc, offset as u16))?;
+    vm.write_memory(addr, value)?;
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
+    use crate::hardware::instruction::execute_instruction;
 
     use super::*;
 
@@ -32,7 +30,7 @@ mod test {
         vm.register.r2 = 1320;
 
         // load memory=98 at pc+pc_offset9=17+13=30, then load r2=1320, then write 1320 to memory at 98
-        sti(0b1011_010_000001101, &mut vm);
+        execute_instruction(0b1011_010_000001101, &mut vm).unwrap();
 
         assert_eq!(vm.memory.read(98), 1320);
     }
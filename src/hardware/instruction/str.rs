@@ -1,4 +1,4 @@
-use super::{safe_u16_add, sign_extend, Vm};
+use super::{safe_u16_add, Vm, VmError};
 
 /// The contents of the register specified by SR are stored in the memory location
 /// whose address is computed by sign-extending bits [5:0] to 16 bits
@@ -9,18 +9,16 @@ use super::{safe_u16_add, sign_extend, Vm};
 /// │      0111     │     SR    │   BaseR   │        Offset6        │
 /// └───────────────┴───────────┴───────────┴───────────────────────┘
 ///
-pub fn str(instr: u16, vm: &mut Vm) {
-    let offset6 = sign_extend(instr & 0x3f, 6);
-    let sr_base = (instr >> 6) & 0x7;
-    let sr = (instr >> 9) & 0x7;
-
-    let value = vm.register.get(sr);
-    let addr = safe_u16_add(vm.register.get(sr_base), offset6);
-    vm.memory.write(addr, value);
+pub fn str(sr: u16, base: u16, offset: i16, vm: &mut Vm) -> Result<(), VmError> {
+    let value = vm.register.get(sr)?;
+    let addr = safe_u16_add(vm.register.get(base)?, offset as u16);
+    vm.write_memory(addr, value)?;
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
+    use crate::hardware::instruction::execute_instruction;
 
     use super::*;
 
@@ -32,7 +30,7 @@ mod test {
         vm.register.r2 = 8901;
 
         // load r0=17, then load r2=8901, then write r2 to memory at r0+offset6=17+29=46
-        str(0b0111_010_000_011101, &mut vm);
+        execute_instruction(0b0111_010_000_011101, &mut vm).unwrap();
 
         assert_eq!(vm.memory.read(46), 8901);
     }
@@ -1,10 +1,9 @@
-use std::io::Read;
+use super::super::{Vm, VmError};
 
-use super::super::Vm;
+pub fn getc(vm: &mut Vm) -> Result<(), VmError> {
+    let char = vm.console.borrow_mut().read_byte()?;
 
-pub fn getc(vm: &mut Vm) {
-    let mut buf = [0; 1];
-    std::io::stdin().read_exact(&mut buf).unwrap();
+    vm.register.r0 = char as u16;
 
-    vm.register.r0 = buf[0] as u16;
+    Ok(())
 }
@@ -1,7 +1,9 @@
-use std::{io::Write, process};
+use super::super::{StepOutcome, Vm, VmError};
 
-pub fn halt() {
-    println!("HALT detected");
-    std::io::stdout().flush().expect("failed to flush");
-    process::exit(1);
+pub fn halt(vm: &mut Vm) -> Result<StepOutcome, VmError> {
+    let mut console = vm.console.borrow_mut();
+    console.write_bytes(b"HALT detected\n")?;
+    console.flush()?;
+
+    Ok(StepOutcome::Halted)
 }
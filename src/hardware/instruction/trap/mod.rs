@@ -1,6 +1,4 @@
-use std::process;
-
-use super::super::Vm;
+use super::super::{error::VmError, interrupt, StepOutcome, Vm};
 
 use getc::getc;
 use halt::halt;
@@ -16,6 +14,27 @@ mod puts;
 mod putsp;
 mod trap_in;
 
+/// A native service routine registered with `Vm::register_trap`.
+/// It has full access to the machine, and returns `StepOutcome::Halted` to stop it.
+pub type TrapHandler = Box<dyn FnMut(&mut Vm) -> Result<StepOutcome, VmError>>;
+
+/// How TRAP finds its service routine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrapMode {
+    /// The standard trap vectors and the ones registered with `Vm::register_trap`
+    /// run as Rust functions; the trap vector table is ignored.
+    #[default]
+    Native,
+    /// Every trap jumps through the trap vector table into LC-3 code, e.g. a loaded OS image,
+    /// in supervisor mode with the PSR and PC pushed on the supervisor stack: its service
+    /// routines must return with RTI, as the built-in OS's do.
+    VectorTable,
+    /// Every trap jumps through the trap vector table with only the return address saved
+    /// in R7, like a JSR, for OS images whose service routines return with RET.
+    /// They run in the mode of the program that called them.
+    VectorTableRet,
+}
+
 enum TrapCode {
     GETC = 32, // 0x20 /* get character from keyboard, not echoed onto the terminal */
     OUT,       /* output a character */
@@ -25,8 +44,8 @@ enum TrapCode {
     HALT,      /* halt the program */
 }
 
-fn get_trap_code(instr: u16) -> Option<TrapCode> {
-    match instr & 0xff {
+fn get_trap_code(vector: u8) -> Option<TrapCode> {
+    match vector {
         32 => Some(TrapCode::GETC),
         33 => Some(TrapCode::OUT),
         34 => Some(TrapCode::PUTS),
@@ -37,22 +56,191 @@ fn get_trap_code(instr: u16) -> Option<TrapCode> {
     }
 }
 
+/// The assembler alias of a standard trap vector, e.g. `HALT` for x25.
+pub fn trap_alias(vector: u8) -> Option<&'static str> {
+    let alias = match get_trap_code(vector)? {
+        TrapCode::GETC => "GETC",
+        TrapCode::OUT => "OUT",
+        TrapCode::PUTS => "PUTS",
+        TrapCode::IN => "IN",
+        TrapCode::PUTSP => "PUTSP",
+        TrapCode::HALT => "HALT",
+    };
+
+    Some(alias)
+}
+
 /// `trap` fn allows interacting with I/O devices
-/// First R7 is loaded with the incremented PC.
-// (This enables a return to the instruction physically following the TRAP instruction in the original program
+/// In `TrapMode::Native` the service routine for trap vector8 runs as a Rust function,
+/// either one registered by the embedder or one of the standard routines.
+/// Through the trap vector table, first R7 is loaded with the incremented PC.
+/// (This enables a return to the instruction physically following the TRAP instruction in the original program
 /// after the service routine has completed execution.)
+/// In `TrapMode::VectorTable` the PSR and PC are pushed onto the supervisor stack and the machine
+/// enters supervisor mode, so the service routine returns with RTI.
+/// In `TrapMode::VectorTableRet` nothing else is saved, so the service routine returns with RET.
 /// Then the PC is loaded with the starting address of the system call specified by trap vector8.
 /// The starting address is contained in the memory location whose address is obtained by zero-extending trap vector8 to 16 bits.
-pub fn trap(instr: u16, vm: &mut Vm) {
-    let trap_code = get_trap_code(instr);
+///
+///  15           12│11           8│7                               0
+/// ┌───────────────┼──────────────┼────────────────────────────────┐
+/// │      1111     │     0000     │           trapvect8            │
+/// └───────────────┴──────────────┴────────────────────────────────┘
+///
+pub fn trap(vector: u8, vm: &mut Vm) -> Result<StepOutcome, VmError> {
+    match vm.trap_mode {
+        TrapMode::Native => {}
+        TrapMode::VectorTable => {
+            vm.register.r7 = vm.register.pc;
+            interrupt::initiate(vm, vector as u16, None)?;
+
+            return Ok(StepOutcome::Continue);
+        }
+        TrapMode::VectorTableRet => {
+            vm.register.r7 = vm.register.pc;
+            vm.register.pc = vm.read_memory(vector as u16)?;
+
+            return Ok(StepOutcome::Continue);
+        }
+    }
+
+    if let Some(mut handler) = vm.traps.remove(&vector) {
+        let outcome = handler(vm);
+        // the handler may have registered a replacement for itself
+        vm.traps.entry(vector).or_insert(handler);
+
+        return outcome;
+    }
+
+    let trap_code = get_trap_code(vector);
 
     match trap_code {
-        Some(TrapCode::GETC) => getc(vm),
-        Some(TrapCode::OUT) => out(vm),
-        Some(TrapCode::PUTS) => puts(vm),
-        Some(TrapCode::IN) => trap_in(vm),
-        Some(TrapCode::PUTSP) => putsp(vm),
-        Some(TrapCode::HALT) => halt(),
-        _ => process::exit(1),
+        Some(TrapCode::GETC) => getc(vm)?,
+        Some(TrapCode::OUT) => out(vm)?,
+        Some(TrapCode::PUTS) => puts(vm)?,
+        Some(TrapCode::IN) => trap_in(vm)?,
+        Some(TrapCode::PUTSP) => putsp(vm)?,
+        Some(TrapCode::HALT) => return halt(vm),
+        None => return Err(VmError::UnknownTrap { vector }),
+    }
+
+    Ok(StepOutcome::Continue)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::hardware::console::BufferConsole;
+
+    use super::*;
+
+    #[test]
+    fn test_puts() {
+        let console = Rc::new(RefCell::new(BufferConsole::new()));
+        let mut vm = Vm::with_console(console.clone());
+
+        for (offset, char) in b"hi\0".iter().enumerate() {
+            vm.memory.write(0x4000 + offset as u16, *char as u16);
+        }
+        vm.register.r0 = 0x4000;
+
+        trap(0x22, &mut vm).unwrap();
+
+        assert_eq!(console.borrow().output(), b"hi");
+    }
+
+    #[test]
+    fn test_getc() {
+        let console = Rc::new(RefCell::new(BufferConsole::with_input(b"x")));
+        let mut vm = Vm::with_console(console.clone());
+
+        trap(0x20, &mut vm).unwrap();
+
+        assert_eq!(vm.register.r0, b'x' as u16);
+        assert!(console.borrow().output().is_empty());
+    }
+
+    #[test]
+    fn test_registered_trap() {
+        let console = Rc::new(RefCell::new(BufferConsole::new()));
+        let mut vm = Vm::with_console(console.clone());
+
+        // x26: print R0 as a decimal number
+        vm.register_trap(0x26, |vm| {
+            let number = vm.register().r0.to_string();
+            vm.console().borrow_mut().write_bytes(number.as_bytes())?;
+
+            Ok(StepOutcome::Continue)
+        });
+        vm.register.r0 = 1234;
+
+        let outcome = trap(0x26, &mut vm).unwrap();
+
+        assert_eq!(outcome, StepOutcome::Continue);
+        assert_eq!(console.borrow().output(), b"1234");
+
+        // registered handlers stay registered and take precedence over the standard ones
+        vm.register_trap(0x25, |_| Ok(StepOutcome::Continue));
+
+        trap(0x26, &mut vm).unwrap();
+        let outcome = trap(0x25, &mut vm).unwrap();
+
+        assert_eq!(outcome, StepOutcome::Continue);
+        assert_eq!(console.borrow().output(), b"12341234");
+    }
+
+    #[test]
+    fn test_vector_table() {
+        let mut vm = Vm::new();
+
+        vm.trap_mode = TrapMode::VectorTable;
+        vm.register.pc = 0x3005;
+        vm.memory.write(0x0025, 0x0520);
+
+        let outcome = trap(0x25, &mut vm).unwrap();
+
+        assert_eq!(outcome, StepOutcome::Continue);
+        assert_eq!(vm.register.pc, 0x0520);
+        assert_eq!(vm.register.r7, 0x3005);
+        assert!(!vm.register.is_user_mode());
+        assert_eq!(vm.memory.read(vm.register.r6), 0x3005);
+    }
+
+    #[test]
+    fn test_vector_table_ret() {
+        let console = Rc::new(RefCell::new(BufferConsole::new()));
+        let mut vm = Vm::with_console(console.clone());
+
+        vm.trap_mode = TrapMode::VectorTableRet;
+        vm.memory.write(0x0021, 0x0430);
+        // OUT that writes R0 to DDR and returns with RET
+        vm.memory.write(0x0430, 0xb001); // STI R0, DDR
+        vm.memory.write(0x0431, 0xc1c0); // RET
+        vm.memory.write(0x0432, 0xfe06); // DDR .FILL xFE06
+        vm.memory.write(0x3000, 0xf021); // OUT
+        vm.register.pc = 0x3000;
+        vm.register.r0 = b'A' as u16;
+        vm.register.r6 = 0x4000;
+
+        let outcome = vm.run_for(3).unwrap();
+
+        assert_eq!(outcome, StepOutcome::Continue);
+        assert_eq!(vm.register.pc, 0x3001);
+        assert_eq!(vm.register.r7, 0x3001);
+        assert_eq!(vm.register.r6, 0x4000);
+        assert!(vm.register.is_user_mode());
+        assert_eq!(console.borrow().output(), b"A");
+    }
+
+    #[test]
+    fn test_halt() {
+        let console = Rc::new(RefCell::new(BufferConsole::new()));
+        let mut vm = Vm::with_console(console.clone());
+
+        let outcome = trap(0x25, &mut vm).unwrap();
+
+        assert_eq!(outcome, StepOutcome::Halted);
+        assert_eq!(console.borrow().output(), b"HALT detected\n");
     }
 }
@@ -1,8 +1,9 @@
-use std::io::Write;
+use super::super::{Vm, VmError};
 
-use super::super::Vm;
+pub fn out(vm: &mut Vm) -> Result<(), VmError> {
+    let mut console = vm.console.borrow_mut();
+    console.write_bytes(&[vm.register.r0 as u8])?;
+    console.flush()?;
 
-pub fn out(vm: &mut Vm) {
-    print!("{}", vm.register.r0 as u8 as char);
-    std::io::stdout().flush().expect("failed to flush");
+    Ok(())
 }
@@ -1,17 +1,20 @@
-use std::io::Write;
+use super::super::{Vm, VmError};
 
-use super::super::Vm;
-
-pub fn puts(vm: &mut Vm) {
+pub fn puts(vm: &mut Vm) -> Result<(), VmError> {
     let mut addr = vm.register.r0;
     let mut char = vm.memory.read(addr) as u8;
+    let mut chars = Vec::new();
 
     while char != 0 {
-        print!("{}", char as char);
+        chars.push(char);
 
-        addr += 1;
+        addr = addr.wrapping_add(1);
         char = vm.memory.read(addr) as u8;
     }
 
-    std::io::stdout().flush().expect("failed to flush");
+    let mut console = vm.console.borrow_mut();
+    console.write_bytes(&chars)?;
+    console.flush()?;
+
+    Ok(())
 }
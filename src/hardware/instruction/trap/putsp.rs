@@ -1,22 +1,25 @@
-use std::io::Write;
+use crate::hardware::{error::VmError, instruction::get_2bytes_chars, Vm};
 
-use crate::hardware::{instruction::get_2bytes_chars, Vm};
-
-pub fn putsp(vm: &mut Vm) {
+pub fn putsp(vm: &mut Vm) -> Result<(), VmError> {
     let mut addr = vm.register.r0;
     let mut value = vm.memory.read(addr);
+    let mut chars = Vec::new();
 
     while value != 0 {
         let [c1, c2] = get_2bytes_chars(value);
-        print!("{}", c1);
+        chars.push(c1 as u8);
 
         if c2 != '\0' {
-            print!("{}", c2);
+            chars.push(c2 as u8);
         }
 
-        addr += 1;
+        addr = addr.wrapping_add(1);
         value = vm.memory.read(addr);
     }
 
-    std::io::stdout().flush().expect("failed to flush")
+    let mut console = vm.console.borrow_mut();
+    console.write_bytes(&chars)?;
+    console.flush()?;
+
+    Ok(())
 }
@@ -1,18 +1,16 @@
-use std::io::{Read, Write};
+use crate::hardware::{error::VmError, instruction::get_cond_flag, Vm};
 
-use crate::hardware::{instruction::get_cond_flag, Vm};
+pub fn trap_in(vm: &mut Vm) -> Result<(), VmError> {
+    let mut console = vm.console.borrow_mut();
+    console.write_bytes(b"Enter a  character : ")?;
+    console.flush()?;
 
-pub fn trap_in(vm: &mut Vm) {
-    print!("Enter a  character : ");
-    std::io::stdout().flush().expect("failed to flush");
-
-    let mut buf = [0; 1];
-    std::io::stdin().read_exact(&mut buf).unwrap();
-
-    let char = buf[0];
-    print!("{}", char as char);
-    std::io::stdout().flush().expect("failed to flush");
+    let char = console.read_byte()?;
+    console.write_bytes(&[char])?;
+    console.flush()?;
 
     vm.register.r0 = char as u16;
-    vm.register.cond = get_cond_flag(char as u16);
+    vm.register.set_cond(get_cond_flag(char as u16));
+
+    Ok(())
 }
@@ -0,0 +1,158 @@
+use super::{error::VmError, StepOutcome, Vm};
+
+pub const INTERRUPT_VECTOR_TABLE: u16 = 0x0100; // x0100 - x01FF
+
+/// A request from a device to run the service routine at `vector` in the interrupt vector table.
+/// It is only taken when `priority` is higher than the priority the processor is running at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interrupt {
+    pub vector: u8,
+    pub priority: u16,
+}
+
+/// Saves the PSR and PC on the supervisor stack, switches to supervisor mode
+/// and jumps to the routine whose address is stored at `vector_addr`.
+/// `priority` replaces the processor priority, exceptions pass `None` to keep it.
+pub fn initiate(vm: &mut Vm, vector_addr: u16, priority: Option<u16>) -> Result<(), VmError> {
+    let psr = vm.register.psr;
+    let pc = vm.register.pc;
+
+    if vm.register.is_user_mode() {
+        vm.register.saved_usp = vm.register.r6;
+        vm.register.r6 = vm.register.saved_ssp;
+    }
+
+    vm.register.psr &= !(1 << 15);
+    if let Some(priority) = priority {
+        vm.register.set_priority(priority);
+    }
+
+    vm.register.r6 = vm.register.r6.wrapping_sub(1);
+    vm.write_memory(vm.register.r6, psr)?;
+    vm.register.r6 = vm.register.r6.wrapping_sub(1);
+    vm.write_memory(vm.register.r6, pc)?;
+
+    vm.register.pc = vm.read_memory(vector_addr)?;
+
+    Ok(())
+}
+
+/// Takes `interrupt` if its priority is above the current one.
+pub fn dispatch(vm: &mut Vm, interrupt: Interrupt) -> Result<bool, VmError> {
+    if interrupt.priority <= vm.register.priority() {
+        return Ok(false);
+    }
+
+    let vector_addr = INTERRUPT_VECTOR_TABLE + interrupt.vector as u16;
+    initiate(vm, vector_addr, Some(interrupt.priority))?;
+
+    Ok(true)
+}
+
+/// Hands `err` to the LC-3 exception handler for it, if the error is an exception
+/// and a handler is installed in the interrupt vector table.
+/// Otherwise the error is returned as is, so the embedder can report the faulting PC and instruction.
+pub fn raise_exception(vm: &mut Vm, err: VmError) -> Result<StepOutcome, VmError> {
+    let Some(vector) = err.exception_vector() else {
+        return Err(err);
+    };
+
+    let vector_addr = INTERRUPT_VECTOR_TABLE + vector as u16;
+    if vm.memory.peek(vector_addr) == 0 {
+        return Err(err);
+    }
+
+    initiate(vm, vector_addr, None)?;
+
+    Ok(StepOutcome::Continue)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dispatch_from_user_mode() {
+        let mut vm = Vm::new();
+
+        vm.register.pc = 0x3005;
+        vm.register.r6 = 0xf000;
+        vm.register.set_cond(0b010);
+        vm.memory.write(0x0180, 0x1000);
+
+        let taken = dispatch(
+            &mut vm,
+            Interrupt {
+                vector: 0x80,
+                priority: 4,
+            },
+        )
+        .unwrap();
+
+        assert!(taken);
+        assert_eq!(vm.register.pc, 0x1000);
+        assert!(!vm.register.is_user_mode());
+        assert_eq!(vm.register.priority(), 4);
+        assert_eq!(vm.register.saved_usp, 0xf000);
+        assert_eq!(vm.register.r6, 0x2ffe);
+        assert_eq!(vm.memory.read(0x2ffe), 0x3005);
+        assert_eq!(vm.memory.read(0x2fff), 0x8002);
+    }
+
+    #[test]
+    fn test_lower_priority_is_masked() {
+        let mut vm = Vm::new();
+
+        vm.register.psr = 0x0500;
+        vm.register.pc = 0x0600;
+
+        let taken = dispatch(
+            &mut vm,
+            Interrupt {
+                vector: 0x80,
+                priority: 4,
+            },
+        )
+        .unwrap();
+
+        assert!(!taken);
+        assert_eq!(vm.register.pc, 0x0600);
+    }
+
+    #[test]
+    fn test_raise_exception() {
+        let mut vm = Vm::new();
+
+        vm.register.pc = 0x3001;
+        vm.memory.write(0x0101, 0x0700);
+
+        let outcome = raise_exception(
+            &mut vm,
+            VmError::IllegalOpcode {
+                pc: 0x3000,
+                instr: 0xD000,
+            },
+        );
+
+        assert_eq!(outcome.unwrap(), StepOutcome::Continue);
+        assert_eq!(vm.register.pc, 0x0700);
+        assert_eq!(vm.memory.read(0x2ffe), 0x3001);
+    }
+
+    #[test]
+    fn test_raise_exception_without_handler() {
+        let mut vm = Vm::new();
+
+        let outcome = raise_exception(&mut vm, VmError::UnknownTrap { vector: 0x30 });
+        assert!(matches!(outcome, Err(VmError::UnknownTrap { .. })));
+
+        let outcome = raise_exception(
+            &mut vm,
+            VmError::IllegalOpcode {
+                pc: 0x3000,
+                instr: 0xD000,
+            },
+        );
+        assert!(matches!(outcome, Err(VmError::IllegalOpcode { .. })));
+    }
+}
@@ -1,40 +1,87 @@
-use std::io::Read;
+use super::{
+    console::SharedConsole,
+    device::{
+        display::{Display, DDR, DSR},
+        keyboard::{Keyboard, KBDR, KBSR},
+        mcr::{MachineControl, CLOCK_ENABLE, MCR},
+        timer::{Timer, TMI, TMR},
+        BusError, Device, DeviceBus,
+    },
+    interrupt::Interrupt,
+};
 
 const MAX_SIZE: usize = 65536; // 16 bit word size
 
-enum MemoryMappedRegister {
-    MrKbsr = 0xfe00, // keyboard status
-    MrKbdr = 0xfe02, // keyboard data
+/// RAM plus the devices attached in the I/O page.
+/// Addresses without a device behave like plain RAM.
+pub struct Memory {
+    cells: [u16; MAX_SIZE],
+    bus: DeviceBus,
 }
 
-pub struct Memory([u16; MAX_SIZE]);
-
 impl Memory {
-    pub fn new() -> Self {
-        Self([0; MAX_SIZE])
+    /// Creates memory with the keyboard, display, timer and MCR attached.
+    pub fn new(console: SharedConsole) -> Self {
+        let mut bus = DeviceBus::new();
+        let devices: [(&[u16], Box<dyn Device>); 4] = [
+            (&[KBSR, KBDR], Box::new(Keyboard::new(console.clone()))),
+            (&[DSR, DDR], Box::new(Display::new(console))),
+            (&[TMR, TMI], Box::new(Timer::new())),
+            (&[MCR], Box::new(MachineControl::new())),
+        ];
+        for (addrs, device) in devices {
+            bus.attach(addrs.iter().copied(), device)
+                .expect("built-in devices live in the I/O page");
+        }
+
+        Self {
+            cells: [0; MAX_SIZE],
+            bus,
+        }
     }
 
-    fn handle_keyboard(&mut self) {
-        let mut buf = [0; 1];
-        std::io::stdin().read_exact(&mut buf).unwrap();
+    /// Attaches a custom device, see [`DeviceBus::attach`].
+    pub fn attach_device<I>(&mut self, addrs: I, device: Box<dyn Device>) -> Result<(), BusError>
+    where
+        I: IntoIterator<Item = u16>,
+    {
+        self.bus.attach(addrs, device)
+    }
 
-        if buf[0] != 0 {
-            self.write(MemoryMappedRegister::MrKbsr as u16, 1 << 15);
-            self.write(MemoryMappedRegister::MrKbdr as u16, buf[0] as u16);
-        } else {
-            self.write(MemoryMappedRegister::MrKbsr as u16, 0);
-        }
+    /// Lets every device advance by one instruction.
+    pub fn tick(&mut self) {
+        self.bus.tick();
+    }
+
+    /// The highest priority interrupt requested by any device.
+    pub fn pending_interrupt(&self) -> Option<Interrupt> {
+        self.bus.pending_interrupt()
+    }
+
+    /// Whether the MCR clock-enable bit is still set.
+    pub fn clock_enabled(&self) -> bool {
+        self.peek(MCR) & CLOCK_ENABLE != 0
     }
 
     pub fn read(&mut self, addr: u16) -> u16 {
-        if addr == MemoryMappedRegister::MrKbsr as u16 {
-            self.handle_keyboard();
+        match self.bus.device_mut(addr) {
+            Some(device) => device.read(addr),
+            None => self.cells[addr as usize],
         }
+    }
 
-        self.0[addr as usize]
+    /// Reads `addr` without triggering any device side effects, for inspecting the machine.
+    pub fn peek(&self, addr: u16) -> u16 {
+        match self.bus.device(addr) {
+            Some(device) => device.peek(addr),
+            None => self.cells[addr as usize],
+        }
     }
 
     pub fn write(&mut self, addr: u16, value: u16) {
-        self.0[addr as usize] = value;
+        match self.bus.device_mut(addr) {
+            Some(device) => device.write(addr, value),
+            None => self.cells[addr as usize] = value,
+        }
     }
 }
@@ -1,57 +1,701 @@
+pub mod console;
+pub mod device;
+pub mod error;
+pub mod image;
 pub mod instruction;
+pub mod interrupt;
 pub mod memory;
+pub mod os;
 pub mod register;
+pub mod symbol;
 
-use std::{fs::File, io::BufReader, path::Path};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    path::Path,
+    rc::Rc,
+};
 
-use byteorder::{BigEndian, ReadBytesExt};
+use console::{SharedConsole, TerminalConsole};
+use error::VmError;
+use image::{Image, ImageFormat, LoadError, LoadedImage, OverlapPolicy};
+use instruction::{TrapHandler, TrapMode};
 use memory::Memory;
-use register::Register;
+use register::{Register, PSR};
+use symbol::{AddressError, SymbolTable};
+
+/// What happened after executing an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    /// The machine is ready to execute the next instruction.
+    Continue,
+    /// The program asked the machine to stop, with the HALT trap or by clearing the MCR clock bit.
+    Halted,
+}
+
+const USER_SPACE: std::ops::Range<u16> = 0x3000..device::IO_PAGE_START;
 
 pub struct Vm {
     register: Register,
     memory: Memory,
+    console: SharedConsole,
+    access_control: bool,
+    trap_mode: TrapMode,
+    traps: HashMap<u8, TrapHandler>,
+    images: Vec<LoadedImage>,
+    overlap_policy: OverlapPolicy,
+    entry_point: Option<u16>,
+    /// Whether the entry point was set with `set_entry_point` or taken from an image in user space,
+    /// rather than from an image in system space, e.g. an OS, that a later program replaces.
+    entry_point_fixed: bool,
+    symbols: SymbolTable,
+    os_loaded: bool,
 }
 
 impl Vm {
+    /// Creates a VM talking to the real terminal.
     pub fn new() -> Self {
+        Self::with_console(Rc::new(RefCell::new(TerminalConsole::new())))
+    }
+
+    /// Creates a VM whose traps and keyboard use `console` for I/O.
+    pub fn with_console(console: SharedConsole) -> Self {
         let register = Register::new();
-        let memory: Memory = Memory::new();
+        let memory: Memory = Memory::new(console.clone());
 
-        Self { register, memory }
+        Self {
+            register,
+            memory,
+            console,
+            access_control: false,
+            trap_mode: TrapMode::default(),
+            traps: HashMap::new(),
+            images: Vec::new(),
+            overlap_policy: OverlapPolicy::default(),
+            entry_point: None,
+            entry_point_fixed: false,
+            symbols: SymbolTable::new(),
+            os_loaded: false,
+        }
     }
 
-    pub fn load_image_from_file<P: AsRef<Path>>(&mut self, file_path: P) {
-        let f = File::open(file_path).expect("couldn't open file");
-        let mut f = BufReader::new(f);
+    /// Registers a native service routine for trap `vector`, replacing any previous one,
+    /// including the standard routines for x20 - x25. Only used in `TrapMode::Native`.
+    pub fn register_trap<F>(&mut self, vector: u8, handler: F)
+    where
+        F: FnMut(&mut Vm) -> Result<StepOutcome, VmError> + 'static,
+    {
+        self.traps.insert(vector, Box::new(handler));
+    }
 
-        let pc_addr = f.read_u16::<BigEndian>().expect("fail to read file");
+    /// Loads the built-in operating system and boots into it: traps and exceptions are then
+    /// handled by LC-3 code, and the user program is started at the entry point in user mode.
+    pub fn load_os(&mut self) -> Result<LoadedImage, LoadError> {
+        let os_image = Image::from_bytes(os::IMAGE)?;
+        let image = self.place_image(os_image.origin, &os_image.words)?;
 
-        let mut addr = pc_addr as u16;
-        loop {
-            match f.read_u16::<BigEndian>() {
-                Ok(instr) => {
-                    self.memory.write(addr, instr);
-                    addr += 1;
-                }
-                Err(err) => {
-                    if err.kind() == std::io::ErrorKind::UnexpectedEof {
-                        println!("Successfully loaded image from file!");
-                    } else {
-                        eprint!("{err}");
-                    }
-                    break;
-                }
+        self.os_loaded = true;
+        self.trap_mode = TrapMode::VectorTable;
+        self.register.pc = os::OS_START;
+        self.register.psr = 0x0002; // supervisor mode, priority 0, Z set
+        if let Some(entry_point) = self.entry_point {
+            self.set_entry_point(entry_point);
+        }
+
+        Ok(image)
+    }
+
+    /// Sets where the user program starts, overriding the origin of the loaded images.
+    /// With the built-in OS loaded, this is where its launcher jumps to instead of the PC.
+    pub fn set_entry_point(&mut self, addr: u16) {
+        self.entry_point = Some(addr);
+        self.entry_point_fixed = true;
+
+        if self.os_loaded {
+            self.memory.write(os::USER_PC, addr);
+        } else {
+            self.register.pc = addr;
+        }
+    }
+
+    pub fn entry_point(&self) -> Option<u16> {
+        self.entry_point
+    }
+
+    /// Chooses between the built-in trap routines and the ones in the trap vector table.
+    pub fn set_trap_mode(&mut self, trap_mode: TrapMode) {
+        self.trap_mode = trap_mode;
+    }
+
+    /// When enabled, user-mode accesses outside of x3000 - xFDFF raise an access control
+    /// violation. Off by default, as programs written for this VM poll KBSR from user mode.
+    pub fn set_access_control(&mut self, enabled: bool) {
+        self.access_control = enabled;
+    }
+
+    pub fn register(&self) -> &Register {
+        &self.register
+    }
+
+    pub fn register_mut(&mut self) -> &mut Register {
+        &mut self.register
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+
+    pub fn console(&self) -> &SharedConsole {
+        &self.console
+    }
+
+    fn check_access(&self, addr: u16) -> Result<(), VmError> {
+        if self.access_control && self.register.is_user_mode() && !USER_SPACE.contains(&addr) {
+            let pc = self.register.pc.wrapping_sub(1);
+            return Err(VmError::AccessControlViolation { pc, addr });
+        }
+
+        Ok(())
+    }
+
+    /// Reads `addr` the way the running program sees it: memory and devices,
+    /// plus the processor status register which lives in the register file.
+    pub fn read_memory(&mut self, addr: u16) -> Result<u16, VmError> {
+        self.check_access(addr)?;
+
+        match addr {
+            PSR => Ok(self.register.psr),
+            _ => Ok(self.memory.read(addr)),
+        }
+    }
+
+    /// Writes `addr` the way the running program sees it, see [`Vm::read_memory`].
+    pub fn write_memory(&mut self, addr: u16, value: u16) -> Result<(), VmError> {
+        self.check_access(addr)?;
+
+        match addr {
+            PSR => self.register.psr = value,
+            _ => self.memory.write(addr, value),
+        }
+
+        Ok(())
+    }
+
+    /// Chooses what happens when an image is loaded over an earlier one.
+    pub fn set_overlap_policy(&mut self, overlap_policy: OverlapPolicy) {
+        self.overlap_policy = overlap_policy;
+    }
+
+    /// Every image loaded so far, in load order.
+    pub fn loaded_images(&self) -> &[LoadedImage] {
+        &self.images
+    }
+
+    /// Labels of the loaded programs, used to describe addresses in diagnostics.
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    pub fn symbols_mut(&mut self) -> &mut SymbolTable {
+        &mut self.symbols
+    }
+
+    /// Parses an address given as a number (`x3000`) or a label with an optional offset (`LOOP+3`).
+    pub fn resolve_address(&self, text: &str) -> Result<u16, AddressError> {
+        self.symbols.resolve(text)
+    }
+
+    /// `addr` relative to the closest label (`LOOP+3`), or in hex if there's none nearby.
+    pub fn describe_address(&self, addr: u16) -> String {
+        self.symbols.describe(addr)
+    }
+
+    /// Loads an `.obj`, `.hex` or `.bin` image, along with the labels of the `.sym` file next to it if there's one.
+    pub fn load_image_from_file<P: AsRef<Path>>(
+        &mut self,
+        file_path: P,
+    ) -> Result<LoadedImage, LoadError> {
+        let file_path = file_path.as_ref();
+        let f = File::open(file_path).map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => LoadError::MissingFile(file_path.to_path_buf()),
+            _ => LoadError::Io(err),
+        })?;
+
+        let mut bytes = Vec::new();
+        BufReader::new(f).read_to_end(&mut bytes)?;
+
+        let format =
+            ImageFormat::from_extension(file_path).unwrap_or_else(|| ImageFormat::detect(&bytes));
+        let image = Image::parse(&bytes, format)?;
+
+        let symbol_path = file_path.with_extension("sym");
+        let symbols = match symbol_path != file_path && symbol_path.is_file() {
+            true => SymbolTable::parse(&fs::read_to_string(symbol_path)?),
+            false => SymbolTable::new(),
+        };
+
+        let loaded = self.load(&image)?;
+        self.symbols.extend(&symbols);
+
+        Ok(loaded)
+    }
+
+    /// Loads an image from any reader, e.g. a network stream or a file already opened.
+    pub fn load_image<R: Read>(&mut self, reader: R) -> Result<LoadedImage, LoadError> {
+        let image = Image::read(reader)?;
+
+        self.load(&image)
+    }
+
+    /// Loads an image that is already in memory, e.g. one embedded with `include_bytes!`.
+    pub fn load_image_from_bytes(&mut self, bytes: &[u8]) -> Result<LoadedImage, LoadError> {
+        let image = Image::parse(bytes, ImageFormat::detect(bytes))?;
+
+        self.load(&image)
+    }
+
+    /// Places a user image. Unless one was set, the entry point is the origin of the first image in
+    /// user space (x3000 and up), or of the first image if none is, so that an OS loaded at x0000
+    /// before the program doesn't start at its trap vector table.
+    fn load(&mut self, image: &Image) -> Result<LoadedImage, LoadError> {
+        let loaded = self.place_image(image.origin, &image.words)?;
+
+        let in_user_space = image.origin >= USER_SPACE.start;
+        if !self.entry_point_fixed && (in_user_space || self.entry_point.is_none()) {
+            self.set_entry_point(image.origin);
+            self.entry_point_fixed = in_user_space;
+        }
+
+        Ok(loaded)
+    }
+
+    /// Loads several images in order, e.g. an OS, a program and its data tables.
+    /// Stops at the first image that fails to load.
+    pub fn load_images_from_files<I, P>(
+        &mut self,
+        file_paths: I,
+    ) -> Result<Vec<LoadedImage>, LoadError>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        file_paths
+            .into_iter()
+            .map(|file_path| self.load_image_from_file(file_path))
+            .collect()
+    }
+
+    /// Writes `words` starting at `origin`, after checking them against the images already loaded.
+    fn place_image(&mut self, origin: u16, words: &[u16]) -> Result<LoadedImage, LoadError> {
+        let mut image = LoadedImage::new(origin, words.len());
+
+        let overlaps: Vec<LoadedImage> = self
+            .images
+            .iter()
+            .filter(|existing| existing.overlaps(&image))
+            .map(|existing| LoadedImage::new(existing.origin, existing.len))
+            .collect();
+        if let (Some(existing), OverlapPolicy::Reject) = (overlaps.first(), self.overlap_policy) {
+            return Err(LoadError::Overlap {
+                existing: existing.clone(),
+                image,
+            });
+        }
+
+        for (offset, word) in words.iter().enumerate() {
+            self.memory.write(origin.wrapping_add(offset as u16), *word);
+        }
+
+        self.images.push(image.clone());
+        image.overlaps = overlaps;
+
+        Ok(image)
+    }
+
+    /// Runs the loaded program until it halts.
+    /// Returns `Ok(())` on a clean halt, or the error that stopped the machine.
+    pub fn launch(&mut self) -> Result<(), VmError> {
+        while self.step()? == StepOutcome::Continue {}
+
+        Ok(())
+    }
+
+    /// Fetches the instruction at PC, increments PC and executes exactly that one instruction.
+    /// A pending device interrupt with a higher priority than the processor's is taken first,
+    /// in which case the instruction executed is the first one of its service routine.
+    /// Exceptions (illegal opcode, privilege mode and access control violations) vector
+    /// to the handler in the interrupt vector table, or are returned if none is installed.
+    /// Nothing is executed while the MCR clock is stopped.
+    pub fn step(&mut self) -> Result<StepOutcome, VmError> {
+        if !self.memory.clock_enabled() {
+            return Ok(StepOutcome::Halted);
+        }
+
+        self.memory.tick();
+        if let Some(interrupt) = self.memory.pending_interrupt() {
+            interrupt::dispatch(self, interrupt)?;
+        }
+
+        let pc = self.register.pc;
+        self.register.pc = pc.wrapping_add(1);
+
+        let outcome = match self.read_memory(pc) {
+            Ok(instr) => instruction::execute_instruction(instr, self),
+            Err(err) => Err(err),
+        };
+        let outcome = match outcome {
+            Ok(outcome) => outcome,
+            Err(err) => interrupt::raise_exception(self, err)?,
+        };
+
+        if self.memory.clock_enabled() {
+            Ok(outcome)
+        } else {
+            Ok(StepOutcome::Halted)
+        }
+    }
+
+    /// Executes at most `n_instructions`, stopping early if the program halts.
+    /// Returns `StepOutcome::Continue` when the budget ran out first.
+    pub fn run_for(&mut self, n_instructions: usize) -> Result<StepOutcome, VmError> {
+        for _ in 0..n_instructions {
+            if self.step()? == StepOutcome::Halted {
+                return Ok(StepOutcome::Halted);
             }
         }
+
+        Ok(StepOutcome::Continue)
     }
 
-    pub fn launch(&mut self) {
+    /// Executes instructions until `predicate` holds or the program halts.
+    /// The predicate is checked after every instruction, so at least one instruction always runs.
+    pub fn run_until<F>(&mut self, mut predicate: F) -> Result<StepOutcome, VmError>
+    where
+        F: FnMut(&Vm) -> bool,
+    {
         loop {
-            let instr = self.memory.read(self.register.pc);
+            if self.step()? == StepOutcome::Halted {
+                return Ok(StepOutcome::Halted);
+            }
 
-            self.register.pc += 1;
-            instruction::execute_instruction(instr, self);
+            if predicate(self) {
+                return Ok(StepOutcome::Continue);
+            }
         }
     }
 }
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use console::BufferConsole;
+
+    use super::*;
+
+    // ADD R0, R0, #1 ; ADD R0, R0, #1 ; ADD R0, R0, #1 ; HALT
+    const PROGRAM: [u16; 4] = [0x1021, 0x1021, 0x1021, 0xF025];
+
+    fn load_program() -> Vm {
+        let mut vm = Vm::with_console(Rc::new(RefCell::new(BufferConsole::new())));
+
+        for (offset, instr) in PROGRAM.iter().enumerate() {
+            vm.memory.write(0x3000 + offset as u16, *instr);
+        }
+
+        vm
+    }
+
+    #[test]
+    fn test_step() {
+        let mut vm = load_program();
+
+        let outcome = vm.step().unwrap();
+
+        assert_eq!(outcome, StepOutcome::Continue);
+        assert_eq!(vm.register().pc, 0x3001);
+        assert_eq!(vm.register().r0, 1);
+    }
+
+    #[test]
+    fn test_run_for() {
+        let mut vm = load_program();
+
+        assert_eq!(vm.run_for(2).unwrap(), StepOutcome::Continue);
+        assert_eq!(vm.register().r0, 2);
+
+        // only two instructions are left, the budget isn't exhausted
+        assert_eq!(vm.run_for(10).unwrap(), StepOutcome::Halted);
+        assert_eq!(vm.register().r0, 3);
+    }
+
+    #[test]
+    fn test_run_until() {
+        let mut vm = load_program();
+
+        let outcome = vm.run_until(|vm| vm.register().pc == 0x3002).unwrap();
+
+        assert_eq!(outcome, StepOutcome::Continue);
+        assert_eq!(vm.register().r0, 2);
+    }
+
+    #[test]
+    fn test_psr_memory_mapped() {
+        let mut vm = load_program();
+
+        vm.register.set_cond(instruction::ConditionFlag::NEG as u16);
+
+        assert_eq!(vm.read_memory(PSR).unwrap(), 0x8004);
+
+        vm.write_memory(PSR, 0x0301).unwrap();
+
+        assert!(!vm.register().is_user_mode());
+        assert_eq!(vm.register().priority(), 3);
+        assert_eq!(vm.register().cond(), instruction::ConditionFlag::POS as u16);
+    }
+
+    #[test]
+    fn test_keyboard_interrupt() {
+        let console = Rc::new(RefCell::new(BufferConsole::new()));
+        let mut vm = Vm::with_console(console.clone());
+
+        // the service routine reads the key: LDI R0, KBDR_ADDR ; RTI ; KBDR_ADDR .FILL xFE02
+        for (offset, instr) in [0xA001, 0x8000, 0xFE02].iter().enumerate() {
+            vm.memory.write(0x1000 + offset as u16, *instr);
+        }
+        vm.memory.write(0x0180, 0x1000);
+        // the user program spins: BRnzp #-1
+        vm.memory.write(0x3000, 0x0FFF);
+        vm.register.set_cond(instruction::ConditionFlag::ZRO as u16);
+        vm.memory.write(device::keyboard::KBSR, 1 << 14);
+
+        vm.run_for(3).unwrap();
+        assert_eq!(vm.register().pc, 0x3000);
+
+        console.borrow_mut().push_input(b"k");
+        vm.step().unwrap();
+
+        assert_eq!(vm.register().pc, 0x1001);
+        assert_eq!(vm.register().r0, b'k' as u16);
+        assert!(!vm.register().is_user_mode());
+
+        vm.step().unwrap();
+
+        assert_eq!(vm.register().pc, 0x3000);
+        assert!(vm.register().is_user_mode());
+        assert_eq!(vm.memory().pending_interrupt(), None);
+    }
+
+    #[test]
+    fn test_illegal_opcode_exception() {
+        let mut vm = load_program();
+
+        vm.memory.write(0x3000, 0xD000);
+
+        let result = vm.step();
+        assert!(matches!(
+            result,
+            Err(VmError::IllegalOpcode { pc: 0x3000, .. })
+        ));
+
+        // with a handler installed, the exception vectors to it in supervisor mode
+        vm.register.pc = 0x3000;
+        vm.memory.write(0x0101, 0x0500);
+        vm.step().unwrap();
+
+        assert_eq!(vm.register().pc, 0x0500);
+        assert!(!vm.register().is_user_mode());
+        assert_eq!(vm.memory().peek(vm.register().r6), 0x3001);
+    }
+
+    #[test]
+    fn test_access_control_violation() {
+        let mut vm = load_program();
+
+        // LD R0, #-3 reads x2FFE from user mode
+        vm.memory.write(0x3000, 0x21FD);
+        vm.step().unwrap();
+
+        vm.register.pc = 0x3000;
+        vm.set_access_control(true);
+        let result = vm.step();
+
+        assert!(matches!(
+            result,
+            Err(VmError::AccessControlViolation {
+                pc: 0x3000,
+                addr: 0x2FFE
+            })
+        ));
+    }
+
+    #[test]
+    fn test_os() {
+        let console = Rc::new(RefCell::new(BufferConsole::with_input(b"k")));
+        let mut vm = Vm::with_console(console.clone());
+        vm.load_os().unwrap();
+
+        // GETC ; OUT ; LEA R0, #2 ; PUTS ; HALT ; "!" .STRINGZ
+        let program = [0xF020, 0xF021, 0xE002, 0xF022, 0xF025, 0x0021, 0x0000];
+        for (offset, instr) in program.iter().enumerate() {
+            vm.memory.write(0x3000 + offset as u16, *instr);
+        }
+
+        vm.launch().unwrap();
+
+        assert_eq!(
+            console.borrow().output(),
+            b"k!\n--- Halting the LC-3 ---\n\n"
+        );
+        assert!(!vm.memory().clock_enabled());
+    }
+
+    #[test]
+    fn test_os_illegal_opcode() {
+        let console = Rc::new(RefCell::new(BufferConsole::new()));
+        let mut vm = Vm::with_console(console.clone());
+        vm.load_os().unwrap();
+
+        vm.memory.write(0x3000, 0xD000);
+        vm.launch().unwrap();
+
+        assert_eq!(console.borrow().output(), b"\n--- Illegal opcode ---\n");
+    }
+
+    #[test]
+    fn test_overlapping_images() {
+        let mut vm = load_program();
+
+        vm.place_image(0x3000, &[1, 2, 3]).unwrap();
+        vm.place_image(0x3003, &[4]).unwrap();
+
+        let result = vm.place_image(0x3002, &[5, 6]);
+        assert!(matches!(
+            result,
+            Err(LoadError::Overlap { ref existing, .. }) if existing.origin == 0x3000
+        ));
+        assert_eq!(vm.memory().peek(0x3002), 3);
+
+        vm.set_overlap_policy(OverlapPolicy::Warn);
+        let image = vm.place_image(0x3002, &[5, 6]).unwrap();
+
+        assert_eq!(image.overlaps.len(), 2);
+        assert_eq!(vm.memory().peek(0x3002), 5);
+        assert_eq!(vm.loaded_images().len(), 3);
+    }
+
+    #[test]
+    fn test_load_image_errors() {
+        let mut vm = load_program();
+
+        let result = vm.load_image_from_file("images/missing.obj");
+        assert!(matches!(result, Err(LoadError::MissingFile(_))));
+
+        let result = vm.load_image(&[0x30, 0x00, 0x12][..]);
+        assert!(matches!(result, Err(LoadError::OddByteCount { len: 3 })));
+
+        let image = vm.load_image_from_bytes(&[0x40, 0x00, 0x12, 0x34]).unwrap();
+        assert_eq!(image, LoadedImage::new(0x4000, 1));
+        assert_eq!(vm.memory().peek(0x4000), 0x1234);
+    }
+
+    #[test]
+    fn test_symbols() {
+        let dir = std::env::temp_dir().join(format!("lc3-symbols-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // LOOP: ADD R1, R1, #1 ; .FILL xD000 (reserved opcode)
+        fs::write(dir.join("prog.obj"), [0x30, 0x00, 0x12, 0x61, 0xd0, 0x00]).unwrap();
+        fs::write(dir.join("prog.sym"), "//\tLOOP              3000\n").unwrap();
+
+        let mut vm = Vm::with_console(Rc::new(RefCell::new(BufferConsole::new())));
+        vm.load_image_from_file(dir.join("prog.obj")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(vm.resolve_address("LOOP+1"), Ok(0x3001));
+        assert_eq!(vm.describe_address(0x3001), "LOOP+1");
+
+        let err = vm.launch().unwrap_err();
+        assert_eq!(
+            err.with_symbols(vm.symbols()).to_string(),
+            "illegal opcode xD000 at LOOP+1"
+        );
+        assert_eq!(err.to_string(), "illegal opcode xD000 at x3001");
+    }
+
+    #[test]
+    fn test_entry_point() {
+        let mut vm = load_program();
+
+        vm.load_image_from_bytes(&[0x40, 0x00, 0xf0, 0x25]).unwrap();
+        vm.load_image_from_bytes(&[0x50, 0x00, 0x00, 0x00]).unwrap();
+
+        assert_eq!(vm.entry_point(), Some(0x4000));
+        assert_eq!(vm.register().pc, 0x4000);
+
+        vm.set_entry_point(0x3000);
+
+        assert_eq!(vm.register().pc, 0x3000);
+    }
+
+    #[test]
+    fn test_entry_point_after_os_image() {
+        let mut vm = Vm::new();
+
+        // an OS image at x0000 starting with its trap vector table, then a program at x3000
+        vm.load_image_from_bytes(&[0x00, 0x00, 0x04, 0x00, 0x04, 0x30])
+            .unwrap();
+        assert_eq!(vm.entry_point(), Some(0x0000));
+
+        vm.load_image_from_bytes(&[0x30, 0x00, 0xf0, 0x25]).unwrap();
+        vm.load_image_from_bytes(&[0x40, 0x00, 0x00, 0x00]).unwrap();
+
+        assert_eq!(vm.entry_point(), Some(0x3000));
+        assert_eq!(vm.register().pc, 0x3000);
+    }
+
+    #[test]
+    fn test_entry_point_with_os() {
+        let console = Rc::new(RefCell::new(BufferConsole::new()));
+        let mut vm = Vm::with_console(console.clone());
+
+        // PUTS of an empty string, HALT
+        vm.load_image_from_bytes(&[0x40, 0x00, 0xe0, 0x01, 0xf0, 0x25, 0x00, 0x00])
+            .unwrap();
+        vm.load_os().unwrap();
+
+        assert_eq!(vm.register().pc, os::OS_START);
+        assert_eq!(vm.memory().peek(os::USER_PC), 0x4000);
+
+        vm.launch().unwrap();
+
+        assert_eq!(console.borrow().output(), b"\n--- Halting the LC-3 ---\n\n");
+    }
+
+    #[test]
+    fn test_mcr_halt() {
+        let mut vm = load_program();
+
+        // AND R0, R0, #0 ; STI R0, MCR_ADDR ; ADD R0, R0, #1 ; MCR_ADDR .FILL xFFFE
+        for (offset, instr) in [0x5020, 0xB201, 0x1021, 0xFFFE].iter().enumerate() {
+            vm.memory.write(0x3000 + offset as u16, *instr);
+        }
+
+        assert_eq!(vm.run_for(10).unwrap(), StepOutcome::Halted);
+        assert_eq!(vm.register().pc, 0x3002);
+        assert_eq!(vm.register().r0, 0);
+
+        // the machine stays stopped until the clock is re-enabled
+        assert_eq!(vm.step().unwrap(), StepOutcome::Halted);
+        assert_eq!(vm.register().pc, 0x3002);
+    }
+}
@@ -0,0 +1,689 @@
+; Default LC-3 operating system for lc3-rust.
+;
+; Provides the trap service routines for GETC, OUT, PUTS, IN, PUTSP and HALT,
+; handlers for the privilege mode, illegal opcode and access control exceptions,
+; and a launcher that drops into the user program in user mode.
+;
+; Trap and interrupt service routines run in supervisor mode and return with RTI.
+
+        .ORIG x0000
+
+; the trap vector table, x0000 - x00FF
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL TRAP_GETC
+        .FILL TRAP_OUT
+        .FILL TRAP_PUTS
+        .FILL TRAP_IN
+        .FILL TRAP_PUTSP
+        .FILL TRAP_HALT
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+        .FILL BAD_TRAP
+
+; the interrupt vector table, x0100 - x01FF
+        .FILL PRIV_VIOLATION
+        .FILL ILLEGAL_OPCODE
+        .FILL ACCESS_VIOLATION
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+        .FILL BAD_INTERRUPT
+
+; x0200: the launcher, started in supervisor mode.
+; Sets up the supervisor stack, then "returns" to the user program in user mode.
+OS_START
+        LD R6, OS_SSP
+        LD R0, USER_PSR
+        ADD R6, R6, #-1
+        STR R0, R6, #0
+        LD R0, USER_PC
+        ADD R6, R6, #-1
+        STR R0, R6, #0
+        RTI
+
+OS_SSP      .FILL x3000
+USER_PSR    .FILL x8002     ; user mode, priority 0, Z set
+USER_PC     .FILL x3000     ; entry point of the user program
+
+; GETC: read a character from the keyboard into R0, without echo
+TRAP_GETC
+        LDI R0, GETC_KBSR
+        BRzp TRAP_GETC
+        LDI R0, GETC_KBDR
+        RTI
+
+GETC_KBSR   .FILL xFE00
+GETC_KBDR   .FILL xFE02
+
+; OUT: write the character in R0 to the display
+TRAP_OUT
+        ST R1, OUT_SAVE_R1
+OUT_WAIT
+        LDI R1, OUT_DSR
+        BRzp OUT_WAIT
+        STI R0, OUT_DDR
+        LD R1, OUT_SAVE_R1
+        RTI
+
+OUT_DSR     .FILL xFE04
+OUT_DDR     .FILL xFE06
+OUT_SAVE_R1 .BLKW 1
+
+; PUTS: write the string of one character per word starting at R0
+TRAP_PUTS
+        ST R0, PUTS_SAVE_R0
+        ST R1, PUTS_SAVE_R1
+        ADD R1, R0, #0
+PUTS_LOOP
+        LDR R0, R1, #0
+        BRz PUTS_DONE
+        OUT
+        ADD R1, R1, #1
+        BRnzp PUTS_LOOP
+PUTS_DONE
+        LD R0, PUTS_SAVE_R0
+        LD R1, PUTS_SAVE_R1
+        RTI
+
+PUTS_SAVE_R0 .BLKW 1
+PUTS_SAVE_R1 .BLKW 1
+
+; IN: prompt for a character, echo it and return it in R0
+TRAP_IN
+        ST R1, IN_SAVE_R1
+        LEA R0, IN_PROMPT
+        PUTS
+        GETC
+        OUT
+        ADD R1, R0, #0
+        LD R0, IN_NEWLINE
+        OUT
+        ADD R0, R1, #0
+        LD R1, IN_SAVE_R1
+        RTI
+
+IN_SAVE_R1  .BLKW 1
+IN_NEWLINE  .FILL x000A
+IN_PROMPT   .STRINGZ "\nInput a character> "
+
+; PUTSP: write the string of two characters per word starting at R0,
+; low byte first
+TRAP_PUTSP
+        ST R0, PUTSP_SAVE_R0
+        ST R1, PUTSP_SAVE_R1
+        ST R2, PUTSP_SAVE_R2
+        ST R3, PUTSP_SAVE_R3
+        ADD R1, R0, #0
+PUTSP_LOOP
+        LDR R2, R1, #0
+        BRz PUTSP_DONE
+        LD R3, PUTSP_LOW_BYTE
+        AND R0, R2, R3
+        OUT
+        AND R0, R0, #0      ; shift the high byte down, one bit at a time
+        AND R3, R3, #0
+        ADD R3, R3, #8
+PUTSP_SHIFT
+        ADD R0, R0, R0
+        ADD R2, R2, #0
+        BRzp PUTSP_NEXT_BIT
+        ADD R0, R0, #1
+PUTSP_NEXT_BIT
+        ADD R2, R2, R2
+        ADD R3, R3, #-1
+        BRp PUTSP_SHIFT
+        ADD R0, R0, #0
+        BRz PUTSP_NEXT_WORD
+        OUT
+PUTSP_NEXT_WORD
+        ADD R1, R1, #1
+        BRnzp PUTSP_LOOP
+PUTSP_DONE
+        LD R0, PUTSP_SAVE_R0
+        LD R1, PUTSP_SAVE_R1
+        LD R2, PUTSP_SAVE_R2
+        LD R3, PUTSP_SAVE_R3
+        RTI
+
+PUTSP_LOW_BYTE .FILL x00FF
+PUTSP_SAVE_R0  .BLKW 1
+PUTSP_SAVE_R1  .BLKW 1
+PUTSP_SAVE_R2  .BLKW 1
+PUTSP_SAVE_R3  .BLKW 1
+
+; HALT: stop the clock by clearing bit 15 of the MCR
+TRAP_HALT
+        LEA R0, HALT_MESSAGE
+        PUTS
+HALT_MACHINE
+        LDI R0, HALT_MCR
+        LD R1, HALT_MASK
+        AND R0, R0, R1
+        STI R0, HALT_MCR
+        BRnzp HALT_MACHINE  ; only reached if the clock is started again
+
+HALT_MCR     .FILL xFFFE
+HALT_MASK    .FILL x7FFF
+HALT_MESSAGE .STRINGZ "\n--- Halting the LC-3 ---\n\n"
+
+; exception handlers: report the exception and halt the machine
+PRIV_VIOLATION
+        LEA R0, PRIV_MESSAGE
+        BRnzp REPORT_AND_HALT
+ILLEGAL_OPCODE
+        LEA R0, ILLEGAL_MESSAGE
+        BRnzp REPORT_AND_HALT
+ACCESS_VIOLATION
+        LEA R0, ACCESS_MESSAGE
+        BRnzp REPORT_AND_HALT
+BAD_TRAP
+        LEA R0, BAD_TRAP_MESSAGE
+REPORT_AND_HALT
+        PUTS
+        BRnzp HALT_MACHINE
+
+PRIV_MESSAGE     .STRINGZ "\n--- Privilege mode violation ---\n"
+ILLEGAL_MESSAGE  .STRINGZ "\n--- Illegal opcode ---\n"
+ACCESS_MESSAGE   .STRINGZ "\n--- Access control violation ---\n"
+BAD_TRAP_MESSAGE .STRINGZ "\n--- Undefined trap executed ---\n"
+
+; unexpected interrupts are ignored
+BAD_INTERRUPT
+        RTI
+
+        .END
@@ -0,0 +1,12 @@
+//! The built-in LC-3 operating system, assembled from `lc3os.asm` with `lc3-rust asm`.
+//! It fills the trap and interrupt vector tables, implements the standard trap routines
+//! and exception handlers in LC-3 code, and launches the user program in user mode.
+
+/// The assembled image, in the same format `Vm::load_image_from_file` reads.
+pub const IMAGE: &[u8] = include_bytes!("lc3os.obj");
+
+/// Where the OS starts executing, in supervisor mode.
+pub const OS_START: u16 = 0x0200;
+
+/// The word the launcher reads the user program's entry point from.
+pub const USER_PC: u16 = 0x020a;
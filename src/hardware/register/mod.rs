@@ -1,4 +1,13 @@
+use super::error::VmError;
+
 const PC_START: u16 = 0x3000;
+const SSP_START: u16 = 0x3000; // the supervisor stack grows down from just below user space
+
+pub const PSR: u16 = 0xfffc; // memory-mapped address of the processor status register
+
+const PSR_USER_MODE: u16 = 1 << 15;
+const PSR_PRIORITY: u16 = 0b111 << 8;
+const PSR_COND: u16 = 0b111;
 
 #[derive(Debug)]
 pub struct Register {
@@ -11,7 +20,12 @@ pub struct Register {
     pub r6: u16,
     pub r7: u16,
     pub pc: u16,
-    pub cond: u16,
+    /// Processor status: privilege mode in bit 15, priority level in bits 10:8
+    /// and the condition codes in bits 2:0.
+    pub psr: u16,
+    /// R6 of the mode that isn't running, swapped in on every privilege change.
+    pub saved_ssp: u16,
+    pub saved_usp: u16,
 }
 
 impl Register {
@@ -26,27 +40,49 @@ impl Register {
             r6: 0,
             r7: 0,
             pc: PC_START,
-            cond: 0,
+            psr: PSR_USER_MODE,
+            saved_ssp: SSP_START,
+            saved_usp: 0,
         }
     }
 
-    pub fn get(&self, index: u16) -> u16 {
+    pub fn cond(&self) -> u16 {
+        self.psr & PSR_COND
+    }
+
+    pub fn set_cond(&mut self, cond: u16) {
+        self.psr = (self.psr & !PSR_COND) | (cond & PSR_COND);
+    }
+
+    pub fn is_user_mode(&self) -> bool {
+        self.psr & PSR_USER_MODE != 0
+    }
+
+    pub fn priority(&self) -> u16 {
+        (self.psr & PSR_PRIORITY) >> 8
+    }
+
+    pub fn set_priority(&mut self, priority: u16) {
+        self.psr = (self.psr & !PSR_PRIORITY) | ((priority << 8) & PSR_PRIORITY);
+    }
+
+    pub fn get(&self, index: u16) -> Result<u16, VmError> {
         match index {
-            0 => self.r0,
-            1 => self.r1,
-            2 => self.r2,
-            3 => self.r3,
-            4 => self.r4,
-            5 => self.r5,
-            6 => self.r6,
-            7 => self.r7,
-            8 => self.pc,
-            9 => self.cond,
-            _ => panic!("Index out of bound. "),
+            0 => Ok(self.r0),
+            1 => Ok(self.r1),
+            2 => Ok(self.r2),
+            3 => Ok(self.r3),
+            4 => Ok(self.r4),
+            5 => Ok(self.r5),
+            6 => Ok(self.r6),
+            7 => Ok(self.r7),
+            8 => Ok(self.pc),
+            9 => Ok(self.cond()),
+            _ => Err(VmError::InvalidRegister { index }),
         }
     }
 
-    pub fn update(&mut self, index: u16, value: u16) {
+    pub fn update(&mut self, index: u16, value: u16) -> Result<(), VmError> {
         match index {
             0 => self.r0 = value,
             1 => self.r1 = value,
@@ -57,8 +93,16 @@ impl Register {
             6 => self.r6 = value,
             7 => self.r7 = value,
             8 => self.pc = value,
-            9 => self.cond = value,
-            _ => panic!("Index out of bound"),
+            9 => self.set_cond(value),
+            _ => return Err(VmError::InvalidRegister { index }),
         }
+
+        Ok(())
+    }
+}
+
+impl Default for Register {
+    fn default() -> Self {
+        Self::new()
     }
 }
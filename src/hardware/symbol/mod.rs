@@ -0,0 +1,285 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt,
+};
+
+/// How far past its closest label an address is still described relative to that label.
+/// Anything further away (e.g. the stack) is more readable as a plain address.
+const MAX_LABEL_OFFSET: u16 = 0x100;
+
+/// Labels and the addresses they stand for, e.g. read from the `.sym` file `lc3as` writes
+/// next to every `.obj`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolTable {
+    addresses: HashMap<String, u16>,
+    labels: BTreeMap<u16, String>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the `lc3as` symbol file format:
+    ///
+    /// ```text
+    /// // Symbol table
+    /// // Scope level 0:
+    /// //    Symbol Name       Page Address
+    /// //    ----------------  ------------
+    /// //    LOOP              3002
+    /// ```
+    ///
+    /// Lines that aren't a label followed by a hex address are ignored.
+    pub fn parse(text: &str) -> Self {
+        let mut symbols = Self::new();
+
+        for line in text.lines() {
+            let line = line.trim_start().trim_start_matches('/');
+            let mut fields = line.split_whitespace();
+
+            let (Some(label), Some(address), None) = (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            if !is_label(label) {
+                continue;
+            }
+            if let Ok(address) = u16::from_str_radix(address, 16) {
+                symbols.insert(label, address);
+            }
+        }
+
+        symbols
+    }
+
+    /// The symbols in the `lc3as` format read by [`SymbolTable::parse`], ordered by address.
+    pub fn to_sym_file(&self) -> String {
+        let mut text = String::from(
+            "// Symbol table\n\
+             // Scope level 0:\n\
+             //\tSymbol Name       Page Address\n\
+             //\t----------------  ------------\n",
+        );
+        for (label, address) in self.iter() {
+            text.push_str(&format!("//\t{label:<16}  {address:04X}\n"));
+        }
+
+        text
+    }
+
+    /// Defines `label`, replacing any earlier definition of it.
+    pub fn insert(&mut self, label: &str, address: u16) {
+        if let Some(previous) = self.addresses.insert(label.to_string(), address) {
+            if self.labels.get(&previous).is_some_and(|name| name == label) {
+                self.labels.remove(&previous);
+                // hand the old address over to another label defined there, if any
+                let other = self
+                    .addresses
+                    .iter()
+                    .filter(|(_, other)| **other == previous)
+                    .map(|(other, _)| other)
+                    .min();
+                if let Some(other) = other {
+                    self.labels.insert(previous, other.clone());
+                }
+            }
+        }
+        // with several labels on one address, the first one defined names it
+        self.labels
+            .entry(address)
+            .or_insert_with(|| label.to_string());
+    }
+
+    /// Adds every symbol of `other`; its definitions win over the ones already present.
+    pub fn extend(&mut self, other: &SymbolTable) {
+        for (label, address) in other.iter() {
+            self.insert(label, address);
+        }
+    }
+
+    pub fn address_of(&self, label: &str) -> Option<u16> {
+        self.addresses.get(label).copied()
+    }
+
+    /// The label defined exactly at `address`.
+    pub fn label_at(&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(String::as_str)
+    }
+
+    /// The closest label at or before `address`, and how far past it `address` is.
+    pub fn nearest_label(&self, address: u16) -> Option<(&str, u16)> {
+        let (label_address, label) = self.labels.range(..=address).next_back()?;
+        let offset = address - label_address;
+
+        (offset <= MAX_LABEL_OFFSET).then_some((label.as_str(), offset))
+    }
+
+    /// `address` relative to the closest label (`LOOP`, `LOOP+3`), or as `x3012` if there's none.
+    pub fn describe(&self, address: u16) -> String {
+        match self.nearest_label(address) {
+            Some((label, 0)) => label.to_string(),
+            Some((label, offset)) => format!("{label}+{offset}"),
+            None => format!("x{address:04X}"),
+        }
+    }
+
+    /// Parses an address given as a label with an optional offset (`LOOP`, `LOOP+3`, `DATA-1`)
+    /// or as a number in hex (`x3000`, `0x3000`) or decimal (`#12288`, `12288`).
+    pub fn resolve(&self, text: &str) -> Result<u16, AddressError> {
+        let text = text.trim();
+        if let Some(address) = parse_number(text) {
+            return Ok(address);
+        }
+
+        let (label, offset) = match text.find(['+', '-']) {
+            Some(index) => {
+                let (label, offset) = text.split_at(index);
+                let offset: i32 = offset
+                    .parse()
+                    .map_err(|_| AddressError::Invalid(text.to_string()))?;
+                (label.trim_end(), offset)
+            }
+            None => (text, 0),
+        };
+        if !is_label(label) {
+            return Err(AddressError::Invalid(text.to_string()));
+        }
+
+        let address = self
+            .address_of(label)
+            .ok_or_else(|| AddressError::UnknownLabel(label.to_string()))?;
+
+        u16::try_from(address as i32 + offset).map_err(|_| AddressError::Invalid(text.to_string()))
+    }
+
+    /// Every symbol, ordered by address.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u16)> {
+        let mut symbols: Vec<(&str, u16)> = self
+            .addresses
+            .iter()
+            .map(|(label, address)| (label.as_str(), *address))
+            .collect();
+        symbols.sort_by_key(|(label, address)| (*address, *label));
+
+        symbols.into_iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+}
+
+fn parse_number(text: &str) -> Option<u16> {
+    if let Some(hex) = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix(['x', 'X']))
+    {
+        u16::from_str_radix(hex, 16).ok()
+    } else {
+        text.strip_prefix('#').unwrap_or(text).parse().ok()
+    }
+}
+
+/// Labels start with a letter or an underscore, followed by letters, digits and underscores.
+pub fn is_label(text: &str) -> bool {
+    let mut chars = text.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+    /// Neither a number nor a label with an optional offset, or the result isn't a 16-bit address.
+    Invalid(String),
+    /// A well-formed label that isn't in the symbol table.
+    UnknownLabel(String),
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressError::Invalid(text) => write!(f, "`{text}` is not a 16-bit address"),
+            AddressError::UnknownLabel(label) => write!(f, "unknown label `{label}`"),
+        }
+    }
+}
+
+impl Error for AddressError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SYM_FILE: &str = "// Symbol table\n\
+        // Scope level 0:\n\
+        //\tSymbol Name       Page Address\n\
+        //\t----------------  ------------\n\
+        //\tLOOP              3002\n\
+        //\tDONE              3010\n\
+        //\tPROMPT            3011\n";
+
+    #[test]
+    fn test_parse() {
+        let symbols = SymbolTable::parse(SYM_FILE);
+
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols.address_of("LOOP"), Some(0x3002));
+        assert_eq!(symbols.label_at(0x3011), Some("PROMPT"));
+        assert_eq!(
+            symbols.iter().collect::<Vec<_>>(),
+            [("LOOP", 0x3002), ("DONE", 0x3010), ("PROMPT", 0x3011)]
+        );
+        assert_eq!(symbols.to_sym_file(), SYM_FILE);
+    }
+
+    #[test]
+    fn test_describe() {
+        let symbols = SymbolTable::parse(SYM_FILE);
+
+        assert_eq!(symbols.describe(0x3002), "LOOP");
+        assert_eq!(symbols.describe(0x3005), "LOOP+3");
+        assert_eq!(symbols.describe(0x3001), "x3001");
+        assert_eq!(symbols.describe(0x3011 + MAX_LABEL_OFFSET + 1), "x3112");
+    }
+
+    #[test]
+    fn test_resolve() {
+        let symbols = SymbolTable::parse(SYM_FILE);
+
+        assert_eq!(symbols.resolve("LOOP"), Ok(0x3002));
+        assert_eq!(symbols.resolve("LOOP+3"), Ok(0x3005));
+        assert_eq!(symbols.resolve("DONE-1"), Ok(0x300f));
+        assert_eq!(symbols.resolve("x3000"), Ok(0x3000));
+        assert_eq!(symbols.resolve("#12288"), Ok(0x3000));
+        assert_eq!(
+            symbols.resolve("START"),
+            Err(AddressError::UnknownLabel("START".to_string()))
+        );
+        assert_eq!(
+            symbols.resolve("LOOP+x"),
+            Err(AddressError::Invalid("LOOP+x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_insert_replaces() {
+        let mut symbols = SymbolTable::new();
+
+        symbols.insert("A", 0x3000);
+        symbols.insert("B", 0x3000);
+        symbols.insert("A", 0x3004);
+
+        assert_eq!(symbols.label_at(0x3004), Some("A"));
+        assert_eq!(symbols.label_at(0x3000), Some("B"));
+        assert_eq!(symbols.address_of("B"), Some(0x3000));
+    }
+}
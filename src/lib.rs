@@ -0,0 +1,9 @@
+// binary literals in tests are grouped by instruction field, not by nibble
+#![allow(clippy::unusual_byte_groupings)]
+// opcode and trap names follow the LC-3 mnemonics
+#![allow(clippy::upper_case_acronyms)]
+
+pub mod assembler;
+pub mod disassembler;
+pub mod hardware;
+pub mod linker;
@@ -0,0 +1,394 @@
+//! Combines relocatable modules, written by `asm --module`, into one absolute image.
+//!
+//! ```text
+//! ; main.asm                      ; count.asm
+//!         .IMPORT COUNT                   .EXPORT COUNT
+//!         LD R0, COUNT            COUNT   .FILL #10
+//!         LD R1, COUNT_ADDR
+//!         HALT
+//! COUNT_ADDR .FILL COUNT
+//! ```
+//!
+//! Modules with a `.ORIG` are loaded there; the others follow the module before them.
+//! Instructions can reach labels of other modules as long as they're within PCoffset range;
+//! anything further away is reached through a `.FILL` word holding its address.
+
+mod module;
+
+use std::{collections::HashMap, error::Error, fmt};
+
+use crate::hardware::{
+    image::{Image, LoadedImage},
+    symbol::SymbolTable,
+};
+pub use module::{Module, ModuleError, ModuleSymbol, Relocation, RelocationKind};
+
+const ADDRESS_SPACE: usize = 1 << 16;
+
+/// Collects modules, in the order they're laid out in memory, and links them.
+#[derive(Debug, Clone)]
+pub struct Linker {
+    /// Where the first module without a `.ORIG` goes.
+    origin: u16,
+    modules: Vec<(String, Module)>,
+}
+
+/// The output of the linker: the image to write to the `.obj` file and the labels of every module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Linked {
+    pub image: Image,
+    pub symbols: SymbolTable,
+}
+
+impl Linker {
+    pub fn new(origin: u16) -> Self {
+        Self {
+            origin,
+            modules: Vec::new(),
+        }
+    }
+
+    /// Adds a module after the ones already added. `name` identifies it in errors, e.g. its file name.
+    pub fn add(&mut self, name: impl Into<String>, module: Module) {
+        self.modules.push((name.into(), module));
+    }
+
+    /// Places every module, resolves the labels they import from each other and patches their words.
+    /// Gaps between modules are filled with zeros.
+    ///
+    /// Every problem found is returned, rather than just the first.
+    pub fn link(&self) -> Result<Linked, Vec<LinkError>> {
+        let bases = self.place()?;
+        let exports = self.exports(&bases)?;
+
+        let start = bases.iter().copied().min().unwrap_or(self.origin);
+        let end = self
+            .modules
+            .iter()
+            .zip(&bases)
+            .map(|((_, module), base)| *base as usize + module.words.len())
+            .max()
+            .unwrap_or(start as usize);
+        let mut words = vec![0; end - start as usize];
+        for ((_, module), base) in self.modules.iter().zip(&bases) {
+            let from = (base - start) as usize;
+            words[from..from + module.words.len()].copy_from_slice(&module.words);
+        }
+
+        let mut errors = Vec::new();
+        for ((name, module), base) in self.modules.iter().zip(&bases) {
+            for relocation in &module.relocations {
+                let address = base + relocation.offset;
+                let word = &mut words[(address - start) as usize];
+
+                let Some(import) = relocation.import else {
+                    // only absolute addresses change when a module moves, PC offsets within it don't
+                    if relocation.kind == RelocationKind::Address {
+                        *word = word.wrapping_add(base.wrapping_sub(module.assembled_origin()));
+                    }
+                    continue;
+                };
+                let symbol = &module.imports[import as usize];
+                let Some(&(target, _)) = exports.get(symbol.as_str()) else {
+                    errors.push(LinkError::UndefinedSymbol {
+                        symbol: symbol.clone(),
+                        module: name.clone(),
+                    });
+                    continue;
+                };
+
+                match relocation.kind {
+                    RelocationKind::Address => *word = word.wrapping_add(target),
+                    kind => {
+                        let bits = kind.bits();
+                        let offset = target as i32 - (address as i32 + 1);
+                        if !(-(1 << (bits - 1))..1 << (bits - 1)).contains(&offset) {
+                            errors.push(LinkError::OutOfRange {
+                                symbol: symbol.clone(),
+                                module: name.clone(),
+                                address,
+                                offset,
+                                bits,
+                            });
+                            continue;
+                        }
+                        let mask = (1 << bits) - 1;
+                        *word = *word & !mask | (offset as u16 & mask);
+                    }
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        // exported labels first, so they win over local labels of the same name in other modules
+        let mut symbols = SymbolTable::new();
+        for (symbol, (address, _)) in self.modules.iter().flat_map(|(_, module)| {
+            module
+                .symbols
+                .iter()
+                .filter(|symbol| symbol.exported)
+                .map(|symbol| (symbol.name.as_str(), exports[symbol.name.as_str()]))
+        }) {
+            symbols.insert(symbol, address);
+        }
+        for ((_, module), base) in self.modules.iter().zip(&bases) {
+            for symbol in &module.symbols {
+                if symbols.address_of(&symbol.name).is_none() {
+                    symbols.insert(&symbol.name, base.wrapping_add(symbol.offset));
+                }
+            }
+        }
+
+        Ok(Linked {
+            image: Image {
+                origin: start,
+                words,
+            },
+            symbols,
+        })
+    }
+
+    /// The address every module is loaded at.
+    fn place(&self) -> Result<Vec<u16>, Vec<LinkError>> {
+        let mut errors = Vec::new();
+        let mut placed: Vec<LoadedImage> = Vec::new();
+        let mut next = self.origin as usize;
+
+        for (name, module) in &self.modules {
+            let base = module.origin.map_or(next, usize::from);
+            let len = module.words.len();
+            if base + len > ADDRESS_SPACE {
+                errors.push(LinkError::AddressOverflow {
+                    module: name.clone(),
+                    origin: base,
+                    len,
+                });
+                // keep going from a made up address to check the other modules
+                placed.push(LoadedImage::new(0, 0));
+                continue;
+            }
+
+            let image = LoadedImage::new(base as u16, len);
+            if let Some(other) = placed.iter().position(|other| other.overlaps(&image)) {
+                errors.push(LinkError::Overlap {
+                    module: name.clone(),
+                    placed: image.clone(),
+                    other: self.modules[other].0.clone(),
+                    other_placed: placed[other].clone(),
+                });
+            }
+            next = image.end();
+            placed.push(image);
+        }
+
+        match errors.is_empty() {
+            true => Ok(placed.into_iter().map(|image| image.origin).collect()),
+            false => Err(errors),
+        }
+    }
+
+    /// The address of every exported label, and the module exporting it.
+    fn exports(&self, bases: &[u16]) -> Result<HashMap<&str, (u16, &str)>, Vec<LinkError>> {
+        let mut errors = Vec::new();
+        let mut exports: HashMap<&str, (u16, &str)> = HashMap::new();
+
+        for ((name, module), base) in self.modules.iter().zip(bases) {
+            for symbol in module.symbols.iter().filter(|symbol| symbol.exported) {
+                match exports.get(symbol.name.as_str()) {
+                    Some((_, first)) => errors.push(LinkError::DuplicateExport {
+                        symbol: symbol.name.clone(),
+                        first: first.to_string(),
+                        second: name.clone(),
+                    }),
+                    None => {
+                        exports.insert(&symbol.name, (base.wrapping_add(symbol.offset), name));
+                    }
+                }
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(exports),
+            false => Err(errors),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    /// A module runs past xFFFF.
+    AddressOverflow {
+        module: String,
+        origin: usize,
+        len: usize,
+    },
+    /// Two modules would be loaded over each other.
+    Overlap {
+        module: String,
+        placed: LoadedImage,
+        other: String,
+        other_placed: LoadedImage,
+    },
+    DuplicateExport {
+        symbol: String,
+        first: String,
+        second: String,
+    },
+    /// No module exports a label another one imports.
+    UndefinedSymbol { symbol: String, module: String },
+    /// An instruction's PCoffset can't reach the label it was linked to.
+    OutOfRange {
+        symbol: String,
+        module: String,
+        /// Where the instruction ended up.
+        address: u16,
+        offset: i32,
+        bits: u32,
+    },
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::AddressOverflow { module, origin, len } => write!(
+                f,
+                "{module}: {len} words starting at x{origin:04X} run past the end of memory"
+            ),
+            LinkError::Overlap {
+                module,
+                placed,
+                other,
+                other_placed,
+            } => write!(
+                f,
+                "{module} at {placed} overlaps {other} at {other_placed}"
+            ),
+            LinkError::DuplicateExport {
+                symbol,
+                first,
+                second,
+            } => write!(f, "`{symbol}` is exported by both {first} and {second}"),
+            LinkError::UndefinedSymbol { symbol, module } => write!(
+                f,
+                "{module}: `{symbol}` is imported but no module exports it"
+            ),
+            LinkError::OutOfRange {
+                symbol,
+                module,
+                address,
+                offset,
+                bits,
+            } => write!(
+                f,
+                "{module}: `{symbol}` is {offset} words away from the instruction at x{address:04X}, \
+                 out of range of its PCoffset{bits}; load its address from a `.FILL {symbol}` word instead"
+            ),
+        }
+    }
+}
+
+impl Error for LinkError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assemble_module;
+
+    fn module(source: &str) -> Module {
+        assemble_module(source).unwrap().module
+    }
+
+    #[test]
+    fn test_link() {
+        let main = module(
+            ".ORIG x3000
+        .IMPORT COUNT
+        .IMPORT PRINT
+        LD R0, COUNT
+        LD R1, COUNT_ADDR
+        JSR PRINT
+        HALT
+COUNT_ADDR .FILL COUNT
+        .END",
+        );
+        let lib = module(
+            "       .EXPORT COUNT
+        .EXPORT PRINT
+PRINT   OUT
+        RET
+COUNT   .FILL #10
+SELF    .FILL SELF",
+        );
+
+        let mut linker = Linker::new(0x4000);
+        linker.add("main", main);
+        linker.add("lib", lib);
+        let linked = linker.link().unwrap();
+
+        // lib goes right after main rather than at x4000, as main has a .ORIG
+        assert_eq!(linked.image.origin, 0x3000);
+        assert_eq!(
+            linked.image.words,
+            [0x2006, 0x2202, 0x4802, 0xf025, 0x3007, 0xf021, 0xc1c0, 0x000a, 0x3008]
+        );
+        assert_eq!(linked.symbols.address_of("COUNT"), Some(0x3007));
+        assert_eq!(linked.symbols.address_of("SELF"), Some(0x3008));
+        assert_eq!(linked.symbols.address_of("COUNT_ADDR"), Some(0x3004));
+    }
+
+    #[test]
+    fn test_link_errors() {
+        let main = module(
+            "       .IMPORT FAR
+        .IMPORT MISSING
+        LD R0, FAR
+        LD R0, MISSING",
+        );
+        let far = module(".EXPORT FAR\n.BLKW 300\nFAR .FILL #1");
+
+        let mut linker = Linker::new(0x3000);
+        linker.add("main", main.clone());
+        linker.add("far", far.clone());
+        linker.add("again", far);
+        assert_eq!(
+            linker.link().unwrap_err(),
+            [LinkError::DuplicateExport {
+                symbol: "FAR".to_string(),
+                first: "far".to_string(),
+                second: "again".to_string(),
+            }]
+        );
+
+        let mut linker = Linker::new(0x3000);
+        linker.add("main", main);
+        linker.add("far", module(".EXPORT FAR\n.BLKW 300\nFAR .FILL #1"));
+        let errors = linker.link().unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                LinkError::OutOfRange {
+                    symbol: "FAR".to_string(),
+                    module: "main".to_string(),
+                    address: 0x3000,
+                    offset: 301,
+                    bits: 9,
+                },
+                LinkError::UndefinedSymbol {
+                    symbol: "MISSING".to_string(),
+                    module: "main".to_string(),
+                },
+            ]
+        );
+        assert!(errors[0].to_string().ends_with("`.FILL FAR` word instead"));
+
+        let mut linker = Linker::new(0x3000);
+        linker.add("a", module(".ORIG x3000\nHALT\nHALT"));
+        linker.add("b", module(".ORIG x3001\nHALT"));
+        assert_eq!(
+            linker.link().unwrap_err()[0].to_string(),
+            "b at x3001 - x3001 (1 words) overlaps a at x3000 - x3001 (2 words)"
+        );
+    }
+}
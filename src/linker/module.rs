@@ -0,0 +1,359 @@
+use std::{error::Error, fmt};
+
+use byteorder::{BigEndian, ByteOrder};
+
+/// The first bytes of every relocatable object.
+const MAGIC: &[u8; 4] = b"LC3R";
+const VERSION: u16 = 1;
+/// Import index of relocations against the module's own addresses.
+const LOCAL: u16 = 0xffff;
+
+/// A relocatable object, as written by `asm --module` to a `.rel` file: the module's words,
+/// assembled as if it started at `origin` (or x0000 when it can go anywhere), and everything
+/// the linker needs to move it and connect it to other modules.
+///
+/// All numbers are big-endian, like `.obj` files:
+///
+/// ```text
+/// "LC3R", version: u16, has origin: u16, origin: u16
+/// word count: u32, words: u16...
+/// symbol count: u32, symbols: (name, offset: u16, exported: u16)...
+/// import count: u32, imports: name...
+/// relocation count: u32, relocations: (offset: u16, kind: u16, import: u16 or xFFFF)...
+/// ```
+///
+/// where a name is its length in bytes as a u16 followed by its UTF-8 bytes, and offsets count
+/// words from the start of the module.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Module {
+    /// Where the module has to be loaded, from its `.ORIG`; `None` if the linker can place it anywhere.
+    pub origin: Option<u16>,
+    pub words: Vec<u16>,
+    /// Every label the module defines.
+    pub symbols: Vec<ModuleSymbol>,
+    /// Labels the module uses but other modules define, from `.IMPORT`.
+    pub imports: Vec<String>,
+    /// The words that depend on where the modules end up.
+    pub relocations: Vec<Relocation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleSymbol {
+    pub name: String,
+    /// Words from the start of the module.
+    pub offset: u16,
+    /// Whether other modules can use it, from `.EXPORT`.
+    pub exported: bool,
+}
+
+/// A word the linker has to patch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relocation {
+    /// Words from the start of the module.
+    pub offset: u16,
+    pub kind: RelocationKind,
+    /// Index in `imports` of the label the word refers to,
+    /// or `None` if it holds an address in the module itself.
+    pub import: Option<u16>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationKind {
+    /// A whole word holding an address, from `.FILL LABEL`.
+    Address,
+    /// The PCoffset9 of a BR, LD, LDI, LEA, ST or STI.
+    PcOffset9,
+    /// The PCoffset11 of a JSR.
+    PcOffset11,
+}
+
+impl RelocationKind {
+    /// Number of bits of the word the linker fills in.
+    pub fn bits(self) -> u32 {
+        match self {
+            RelocationKind::Address => 16,
+            RelocationKind::PcOffset9 => 9,
+            RelocationKind::PcOffset11 => 11,
+        }
+    }
+
+    fn to_word(self) -> u16 {
+        match self {
+            RelocationKind::Address => 0,
+            RelocationKind::PcOffset9 => 9,
+            RelocationKind::PcOffset11 => 11,
+        }
+    }
+
+    fn from_word(word: u16) -> Option<Self> {
+        match word {
+            0 => Some(RelocationKind::Address),
+            9 => Some(RelocationKind::PcOffset9),
+            11 => Some(RelocationKind::PcOffset11),
+            _ => None,
+        }
+    }
+}
+
+impl Module {
+    /// The address the words were assembled for.
+    pub fn assembled_origin(&self) -> u16 {
+        self.origin.unwrap_or(0)
+    }
+
+    /// The module in the `.rel` format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer {
+            bytes: MAGIC.to_vec(),
+        };
+        writer.word(VERSION);
+        writer.word(self.origin.is_some() as u16);
+        writer.word(self.assembled_origin());
+        writer.count(self.words.len());
+        for &word in &self.words {
+            writer.word(word);
+        }
+        writer.count(self.symbols.len());
+        for symbol in &self.symbols {
+            writer.name(&symbol.name);
+            writer.word(symbol.offset);
+            writer.word(symbol.exported as u16);
+        }
+        writer.count(self.imports.len());
+        for import in &self.imports {
+            writer.name(import);
+        }
+        writer.count(self.relocations.len());
+        for relocation in &self.relocations {
+            writer.word(relocation.offset);
+            writer.word(relocation.kind.to_word());
+            writer.word(relocation.import.unwrap_or(LOCAL));
+        }
+
+        writer.bytes
+    }
+
+    /// Parses a module in the `.rel` format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ModuleError> {
+        let rest = bytes.strip_prefix(MAGIC).ok_or(ModuleError::NotAModule)?;
+        let mut reader = Reader { bytes: rest };
+
+        let version = reader.word()?;
+        if version != VERSION {
+            return Err(ModuleError::UnsupportedVersion(version));
+        }
+        let has_origin = reader.word()? != 0;
+        let origin = reader.word()?;
+        let origin = has_origin.then_some(origin);
+
+        let words = (0..reader.count()?)
+            .map(|_| reader.word())
+            .collect::<Result<Vec<_>, _>>()?;
+        if origin.unwrap_or(0) as usize + words.len() > 1 << 16 {
+            return Err(ModuleError::Invalid("it runs past the end of memory"));
+        }
+
+        let mut symbols = Vec::new();
+        for _ in 0..reader.count()? {
+            let name = reader.name()?;
+            let offset = reader.word()?;
+            let exported = reader.word()? != 0;
+            if offset as usize > words.len() {
+                return Err(ModuleError::Invalid(
+                    "a symbol is past the end of the module",
+                ));
+            }
+            symbols.push(ModuleSymbol {
+                name,
+                offset,
+                exported,
+            });
+        }
+
+        let imports = (0..reader.count()?)
+            .map(|_| reader.name())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut relocations = Vec::new();
+        for _ in 0..reader.count()? {
+            let offset = reader.word()?;
+            let kind = RelocationKind::from_word(reader.word()?)
+                .ok_or(ModuleError::Invalid("unknown relocation kind"))?;
+            let import = match reader.word()? {
+                LOCAL => None,
+                index if (index as usize) < imports.len() => Some(index),
+                _ => {
+                    return Err(ModuleError::Invalid(
+                        "a relocation refers to a missing import",
+                    ))
+                }
+            };
+            if offset as usize >= words.len() {
+                return Err(ModuleError::Invalid(
+                    "a relocation is past the end of the module",
+                ));
+            }
+            relocations.push(Relocation {
+                offset,
+                kind,
+                import,
+            });
+        }
+
+        if !reader.bytes.is_empty() {
+            return Err(ModuleError::Invalid(
+                "there are bytes after the relocations",
+            ));
+        }
+
+        Ok(Self {
+            origin,
+            words,
+            symbols,
+            imports,
+            relocations,
+        })
+    }
+}
+
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn word(&mut self, word: u16) {
+        self.bytes.extend_from_slice(&word.to_be_bytes());
+    }
+
+    fn count(&mut self, count: usize) {
+        self.bytes.extend_from_slice(&(count as u32).to_be_bytes());
+    }
+
+    fn name(&mut self, name: &str) {
+        self.word(name.len() as u16);
+        self.bytes.extend_from_slice(name.as_bytes());
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], ModuleError> {
+        if self.bytes.len() < len {
+            return Err(ModuleError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Ok(taken)
+    }
+
+    fn word(&mut self) -> Result<u16, ModuleError> {
+        self.take(2).map(BigEndian::read_u16)
+    }
+
+    fn count(&mut self) -> Result<u32, ModuleError> {
+        self.take(4).map(BigEndian::read_u32)
+    }
+
+    fn name(&mut self) -> Result<String, ModuleError> {
+        let len = self.word()? as usize;
+        let bytes = self.take(len)?;
+
+        String::from_utf8(bytes.to_vec()).map_err(|_| ModuleError::Invalid("a name isn't UTF-8"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModuleError {
+    /// The file doesn't start with the `.rel` magic bytes.
+    NotAModule,
+    UnsupportedVersion(u16),
+    /// The file ends in the middle of the module.
+    Truncated,
+    /// The module is structurally wrong, e.g. a relocation past its last word.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModuleError::NotAModule => write!(f, "it isn't a relocatable object (.rel)"),
+            ModuleError::UnsupportedVersion(version) => {
+                write!(f, "relocatable object version {version} isn't supported")
+            }
+            ModuleError::Truncated => write!(f, "the relocatable object is truncated"),
+            ModuleError::Invalid(reason) => {
+                write!(f, "the relocatable object is invalid: {reason}")
+            }
+        }
+    }
+}
+
+impl Error for ModuleError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let module = Module {
+            origin: None,
+            words: vec![0x2001, 0xf025, 0x0000],
+            symbols: vec![ModuleSymbol {
+                name: "MAIN".to_string(),
+                offset: 0,
+                exported: true,
+            }],
+            imports: vec!["COUNT".to_string()],
+            relocations: vec![
+                Relocation {
+                    offset: 0,
+                    kind: RelocationKind::PcOffset9,
+                    import: Some(0),
+                },
+                Relocation {
+                    offset: 2,
+                    kind: RelocationKind::Address,
+                    import: None,
+                },
+            ],
+        };
+
+        let bytes = module.to_bytes();
+        assert_eq!(&bytes[..10], b"LC3R\x00\x01\x00\x00\x00\x00");
+        assert_eq!(Module::from_bytes(&bytes), Ok(module));
+    }
+
+    #[test]
+    fn test_errors() {
+        let module = Module {
+            origin: Some(0x3000),
+            words: vec![0xf025],
+            ..Module::default()
+        };
+        let bytes = module.to_bytes();
+
+        assert_eq!(Module::from_bytes(&bytes), Ok(module));
+        assert_eq!(
+            Module::from_bytes(&[0x30, 0x00, 0xf0, 0x25]),
+            Err(ModuleError::NotAModule)
+        );
+        assert_eq!(
+            Module::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(ModuleError::Truncated)
+        );
+
+        let mut relocated = bytes[..bytes.len() - 4].to_vec();
+        relocated.extend_from_slice(&[0, 0, 0, 1, 0, 1, 0, 0, 0xff, 0xff]);
+        assert_eq!(
+            Module::from_bytes(&relocated),
+            Err(ModuleError::Invalid(
+                "a relocation is past the end of the module"
+            ))
+        );
+    }
+}
@@ -1,20 +1,310 @@
+use std::{fs, path::PathBuf, process::ExitCode};
+
 use clap::Parser;
+use lc3_rust::{
+    assembler::{self, Diagnostic},
+    disassembler,
+    hardware::{self, image::OverlapPolicy, instruction::TrapMode, symbol::SymbolTable},
+    linker::{Linker, Module},
+};
 use utils::{
-    cli::Cli,
+    cli::{AsmArgs, Cli, Command, DisasmArgs, LinkArgs, RunArgs, TrapReturn},
     terminal::{end_session, start_session},
 };
 
-mod hardware;
 mod utils;
 
-fn main() {
-    let termios = start_session();
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Some(Command::Asm(args)) => assemble(args),
+        Some(Command::Link(args)) => link(args),
+        Some(Command::Disasm(args)) => disassemble(args),
+        None => run(cli.run),
+    }
+}
+
+fn assemble(
+    AsmArgs {
+        source,
+        output,
+        module,
+    }: AsmArgs,
+) -> ExitCode {
+    let file = source.display().to_string();
+    let assembly = match module {
+        true => assembler::assemble_module_file(&source),
+        false => assembler::assemble_file(&source),
+    };
+    let assembly = match assembly {
+        Ok(Ok(assembly)) => assembly,
+        Err(err) => {
+            eprintln!("couldn't read {file}: {err}");
+            return ExitCode::FAILURE;
+        }
+        Ok(Err(diagnostics)) => {
+            report(&file, &diagnostics);
+            return ExitCode::FAILURE;
+        }
+    };
+    report(&file, &assembly.warnings);
+
+    let object_path = output.unwrap_or_else(|| match module {
+        true => source.with_extension("rel"),
+        false => source.with_extension("obj"),
+    });
+    let mut files = match module {
+        true => vec![(object_path.clone(), assembly.module.to_bytes())],
+        // a module's symbols are in the relocatable object
+        false => vec![
+            (object_path.clone(), assembly.image.to_bytes()),
+            (
+                object_path.with_extension("sym"),
+                assembly.symbols.to_sym_file().into_bytes(),
+            ),
+        ],
+    };
+    files.push((
+        object_path.with_extension("lst"),
+        assembly.listing.to_lst_file().into_bytes(),
+    ));
+    if !write_files(&files) {
+        return ExitCode::FAILURE;
+    }
+
+    println!("Assembled {} into {}", source.display(), list_paths(&files));
+    ExitCode::SUCCESS
+}
+
+fn link(
+    LinkArgs {
+        modules,
+        output,
+        origin,
+    }: LinkArgs,
+) -> ExitCode {
+    let origin = match SymbolTable::new().resolve(&origin) {
+        Ok(origin) => origin,
+        Err(err) => {
+            eprintln!("invalid --origin: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut linker = Linker::new(origin);
+    for path in &modules {
+        let module = fs::read(path)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| Module::from_bytes(&bytes).map_err(|err| err.to_string()));
+        match module {
+            Ok(module) => linker.add(path.display().to_string(), module),
+            Err(err) => {
+                eprintln!("couldn't read {}: {err}", path.display());
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let linked = match linker.link() {
+        Ok(linked) => linked,
+        Err(errors) => {
+            for err in &errors {
+                eprintln!("error: {err}");
+            }
+            let plural = if errors.len() == 1 { "" } else { "s" };
+            eprintln!("{} error{plural}, nothing was written", errors.len());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let object_path = output.unwrap_or_else(|| modules[0].with_extension("obj"));
+    let files = [
+        (object_path.clone(), linked.image.to_bytes()),
+        (
+            object_path.with_extension("sym"),
+            linked.symbols.to_sym_file().into_bytes(),
+        ),
+    ];
+    if !write_files(&files) {
+        return ExitCode::FAILURE;
+    }
+
+    let sources: Vec<String> = modules
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
+    println!("Linked {} into {}", sources.join(", "), list_paths(&files));
+    ExitCode::SUCCESS
+}
 
-    let Cli { image_path } = Cli::parse();
+fn disassemble(
+    DisasmArgs {
+        image_path,
+        from,
+        to,
+    }: DisasmArgs,
+) -> ExitCode {
+    let mut vm = hardware::Vm::new();
+    let image = match vm.load_image_from_file(&image_path) {
+        Ok(image) => image,
+        Err(err) => {
+            eprintln!("couldn't load {}: {err}", image_path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let whole_image = from.is_none() && to.is_none();
+    let resolve = |name: &str, text: Option<String>| {
+        text.map(|text| {
+            vm.resolve_address(&text)
+                .map(usize::from)
+                .map_err(|err| format!("invalid --{name}: {err}"))
+        })
+        .transpose()
+    };
+    let bounds = resolve("from", from).and_then(|start| Ok((start, resolve("to", to)?)));
+    let (start, end) = match bounds {
+        Ok(bounds) => bounds,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let range = start.unwrap_or(image.origin as usize)..end.map_or(image.end(), |end| end + 1);
+    // an empty range is only an error when asked for, an empty image just shows nothing
+    if range.is_empty() && !whole_image {
+        match (start, end) {
+            (Some(_), Some(_)) => eprintln!("--from is past --to"),
+            (None, _) => eprintln!(
+                "--to is before the image, which starts at x{:04X}",
+                image.origin
+            ),
+            (Some(_), None) if image.end() > image.origin as usize => eprintln!(
+                "--from is past the end of the image, at x{:04X}",
+                image.end() - 1
+            ),
+            (Some(_), None) => eprintln!("--from is past the end of the image, which is empty"),
+        }
+        return ExitCode::FAILURE;
+    }
+
+    let words: Vec<u16> = range
+        .clone()
+        .map(|addr| vm.memory().peek(addr as u16))
+        .collect();
+    let body = disassembler::disassemble_words(range.start as u16, &words, vm.symbols());
+    if whole_image {
+        print!("        .ORIG x{:04X}\n{body}        .END\n", image.origin);
+    } else {
+        print!("{body}");
+    }
+    ExitCode::SUCCESS
+}
+
+/// Writes every file, stopping at the first one that can't be written.
+fn write_files(files: &[(PathBuf, Vec<u8>)]) -> bool {
+    for (path, contents) in files {
+        if let Err(err) = fs::write(path, contents) {
+            eprintln!("couldn't write {}: {err}", path.display());
+            return false;
+        }
+    }
+
+    true
+}
+
+/// `a, b and c`
+fn list_paths(files: &[(PathBuf, Vec<u8>)]) -> String {
+    let paths: Vec<String> = files
+        .iter()
+        .map(|(path, _)| path.display().to_string())
+        .collect();
 
+    match paths.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} and {last}", rest.join(", ")),
+        None => String::new(),
+    }
+}
+
+/// Prints every diagnostic, followed by how many errors and warnings there are.
+fn report(file: &str, diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        eprintln!("{}", diagnostic.render());
+    }
+
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    let warnings = diagnostics.len() - errors;
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    match (errors, warnings) {
+        (0, 0) => {}
+        (0, _) => eprintln!("{file}: {warnings} warning{}", plural(warnings)),
+        _ => eprintln!(
+            "{file}: {errors} error{} and {warnings} warning{}, nothing was written",
+            plural(errors),
+            plural(warnings)
+        ),
+    }
+}
+
+fn run(
+    RunArgs {
+        image_paths,
+        allow_overlap,
+        entry,
+        os,
+        os_traps,
+    }: RunArgs,
+) -> ExitCode {
     let mut vm = hardware::Vm::new();
-    vm.load_image_from_file(image_path);
-    vm.launch();
+    if allow_overlap {
+        vm.set_overlap_policy(OverlapPolicy::Warn);
+    }
+    if os {
+        if let Err(err) = vm.load_os() {
+            eprintln!("couldn't load the built-in OS: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+    for image_path in image_paths {
+        match vm.load_image_from_file(&image_path) {
+            Ok(image) => {
+                println!("Loaded {} at {image}", image_path.display());
+                for existing in image.overlaps {
+                    eprintln!("warning: it overwrites the image at {existing}");
+                }
+            }
+            Err(err) => {
+                eprintln!("couldn't load {}: {err}", image_path.display());
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    if let Some(entry) = entry {
+        match vm.resolve_address(&entry) {
+            Ok(entry) => vm.set_entry_point(entry),
+            Err(err) => {
+                eprintln!("invalid --entry: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    match os_traps {
+        Some(TrapReturn::Rti) => vm.set_trap_mode(TrapMode::VectorTable),
+        Some(TrapReturn::Ret) => vm.set_trap_mode(TrapMode::VectorTableRet),
+        None => {}
+    }
 
+    let termios = start_session();
+    let result = vm.launch();
     end_session(termios);
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err.with_symbols(vm.symbols()));
+            ExitCode::FAILURE
+        }
+    }
 }
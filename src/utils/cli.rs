@@ -1,8 +1,102 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Cli {
-    #[arg(short = 'i', long = "image")]
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Assemble an LC-3 source file into an object file and a symbol table
+    Asm(AsmArgs),
+    /// Link relocatable modules, assembled with `asm --module`, into one object file
+    Link(LinkArgs),
+    /// Print an image as LC-3 assembly, using the labels of the .sym file next to it if there's one
+    Disasm(DisasmArgs),
+}
+
+// running images, the default when no subcommand is given
+#[derive(Args)]
+pub struct RunArgs {
+    /// LC-3 object file to load; repeat to load several images in order
+    #[arg(short = 'i', long = "image", required = true)]
+    pub image_paths: Vec<PathBuf>,
+
+    /// Only warn, instead of failing, when an image overwrites an earlier one
+    #[arg(long)]
+    pub allow_overlap: bool,
+
+    /// Address or label to start the program at (e.g. x3000, MAIN), instead of the origin of the first image at x3000 or above
+    #[arg(long)]
+    pub entry: Option<String>,
+
+    /// Boot the built-in LC-3 operating system before running the image
+    #[arg(long)]
+    pub os: bool,
+
+    /// Run TRAPs through the trap vector table of a loaded OS image instead of natively.
+    /// Its service routines return with RTI, or with RET for `--os-traps=ret`
+    #[arg(long, value_name = "RETURN", num_args = 0..=1, require_equals = true, default_missing_value = "rti")]
+    pub os_traps: Option<TrapReturn>,
+}
+
+/// How the service routines of an OS image return to the program that called TRAP.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum TrapReturn {
+    /// In supervisor mode, from the supervisor stack
+    Rti,
+    /// Like a subroutine, to R7
+    Ret,
+}
+
+#[derive(Args)]
+pub struct AsmArgs {
+    /// LC-3 assembly source file
+    pub source: PathBuf,
+
+    /// Object file to write; defaults to the source file with an .obj extension, or .rel for a module.
+    /// The symbol table and the listing are written next to it, with .sym and .lst extensions
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Assemble a module for `link`, which may leave out .ORIG and use .IMPORT,
+    /// into a relocatable object
+    #[arg(short, long)]
+    pub module: bool,
+}
+
+#[derive(Args)]
+pub struct LinkArgs {
+    /// Relocatable objects to link, laid out in memory in this order
+    #[arg(required = true)]
+    pub modules: Vec<PathBuf>,
+
+    /// Object file to write; defaults to the first module with an .obj extension.
+    /// The symbol table is written next to it, with a .sym extension
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Address of the first module without a .ORIG
+    #[arg(long, default_value = "x3000")]
+    pub origin: String,
+}
+
+#[derive(Args)]
+pub struct DisasmArgs {
+    /// LC-3 object file to disassemble
     pub image_path: PathBuf,
+
+    /// First address or label to disassemble, instead of the image's origin
+    #[arg(long)]
+    pub from: Option<String>,
+
+    /// Last address or label to disassemble, instead of the image's last word
+    #[arg(long)]
+    pub to: Option<String>,
 }
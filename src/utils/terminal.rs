@@ -8,13 +8,14 @@ pub fn start_session() -> Termios {
 
     // make a mutable copy of termios
     // that we will modify
-    let mut new_termios = termios.clone();
+    let mut new_termios = termios;
     new_termios.c_iflag &= IGNBRK | BRKINT | PARMRK | ISTRIP | INLCR | IGNCR | ICRNL | IXON;
     new_termios.c_lflag &= !(ICANON | ECHO); // no echo and canonical mode
 
-    tcsetattr(STD_IN, TCSANOW, &mut new_termios).unwrap();
+    tcsetattr(STD_IN, TCSANOW, &new_termios).unwrap();
 
-    new_termios
+    // hand back the original settings so `end_session` can restore them
+    termios
 }
 
 pub fn end_session(termios: Termios) {